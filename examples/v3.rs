@@ -2,6 +2,7 @@ extern crate sendgrid;
 
 use std::collections::HashMap;
 
+use sendgrid::sg_client::SGClient;
 use sendgrid::v3::*;
 
 fn main() {
@@ -29,7 +30,7 @@ fn main() {
 
     let mut env_vars = ::std::env::vars();
     let api_key = env_vars.find(|v| v.0 == "SG_API_KEY").unwrap();
-    let sender = V3Sender::new(api_key.1);
-    let code = sender.send(&m);
+    let sg = SGClient::new(api_key.1);
+    let code = sg.send(&m);
     println!("{:?}", code);
 }
@@ -0,0 +1,155 @@
+//! Access to SendGrid's Email Activity API, which reports what happened to a sent message.
+
+use crate::errors::{SendgridError, SendgridErrorKind, SendgridResult};
+use crate::sg_client::{parse_api_response, SGClient};
+
+use reqwest::Method;
+
+static MESSAGES_PATH: &str = "v3/messages";
+static ACTIVITY_SCOPE: &str = "messages.read";
+
+/// Where a message has got to on its way to the recipient.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStatus {
+    /// SendGrid accepted the message and is trying to deliver it.
+    Processed,
+    /// The receiving server accepted the message.
+    Delivered,
+    /// The message was dropped or bounced, and won't be delivered.
+    NotDelivered,
+    /// A status this crate doesn't know about yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// The kind of an event in the history of a message.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageEventKind {
+    Processed,
+    Deferred,
+    Delivered,
+    Dropped,
+    Bounced,
+    Open,
+    Click,
+    SpamReport,
+    Unsubscribe,
+    GroupUnsubscribe,
+    GroupResubscribe,
+    /// An event this crate doesn't know about yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// An event in the history of a message.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MessageEvent {
+    /// What happened.
+    pub event_name: MessageEventKind,
+    /// When it happened, as an ISO 8601 timestamp.
+    pub processed: String,
+    /// Why the message was deferred, dropped or bounced, as the receiving server put it.
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// The URL that was clicked, for click events.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// What happened to a sent message.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MessageActivity {
+    /// The id of the message.
+    pub msg_id: String,
+    /// The address the message was sent from.
+    pub from_email: String,
+    /// The address the message was sent to.
+    pub to_email: String,
+    /// The subject of the message.
+    #[serde(default)]
+    pub subject: String,
+    /// Where the message has got to.
+    pub status: MessageStatus,
+    /// How many times the message was opened.
+    #[serde(default)]
+    pub opens_count: u32,
+    /// How many times links in the message were clicked.
+    #[serde(default)]
+    pub clicks_count: u32,
+    /// Everything that has happened to the message, oldest first.
+    #[serde(default)]
+    pub events: Vec<MessageEvent>,
+}
+
+// Decode the activity of a message. Accounts without Email Activity access and keys without the
+// scope are both rejected with a 403, which is reported as its own error kind caused by the API
+// error.
+fn parse_activity(status: u16, raw_body: String) -> SendgridResult<MessageActivity> {
+    parse_api_response(status, raw_body).map_err(|err| match *err.kind() {
+        SendgridErrorKind::Api(403, _, _) => SendgridError::with_chain(
+            err,
+            SendgridErrorKind::MissingScope(String::from(ACTIVITY_SCOPE)),
+        ),
+        _ => err,
+    })
+}
+
+impl SGClient {
+    /// Looks up what happened to a sent message, for example to check that an important message
+    /// was delivered. The id is the message's id as the Email Activity API reports it, which
+    /// starts with the `message_id` of the `SendResponse` and can take a few minutes to appear.
+    ///
+    /// The Email Activity API is only available on paid plans with the add-on, and needs an API
+    /// key with the `messages.read` scope. Without either it fails with
+    /// `SendgridErrorKind::MissingScope`.
+    #[cfg(feature = "blocking")]
+    pub fn get_message_status(&self, message_id: &str) -> SendgridResult<MessageActivity> {
+        let path = format!("{}/{}", MESSAGES_PATH, message_id);
+        let (status, body) = self.api_request(Method::GET, &path, None)?;
+        parse_activity(status, body)
+    }
+
+    /// The asynchronous version of `get_message_status`.
+    pub async fn get_message_status_async(
+        &self,
+        message_id: &str,
+    ) -> SendgridResult<MessageActivity> {
+        let path = format!("{}/{}", MESSAGES_PATH, message_id);
+        let (status, body) = self.api_request_async(Method::GET, &path, None).await?;
+        parse_activity(status, body)
+    }
+}
+
+#[test]
+fn parse_activity_response() {
+    let body = String::from(
+        r#"{"from_email": "me@example.com", "msg_id": "14c5d75ce93.filter0001.16648.5515E0B88.0",
+            "subject": "Hello", "to_email": "you@example.com", "status": "not_delivered",
+            "opens_count": 0, "clicks_count": 0, "teammate": "", "api_key_id": "key",
+            "events": [
+                {"event_name": "processed", "processed": "2024-01-01T00:00:00Z"},
+                {"event_name": "bounced", "processed": "2024-01-01T00:00:01Z",
+                 "reason": "550 5.1.1 mailbox unavailable"},
+                {"event_name": "machine_opened", "processed": "2024-01-01T00:00:02Z"}
+            ]}"#,
+    );
+    let got = parse_activity(200, body).unwrap();
+    assert_eq!(got.status, MessageStatus::NotDelivered);
+    assert_eq!(got.events.len(), 3);
+    assert_eq!(got.events[1].event_name, MessageEventKind::Bounced);
+    assert_eq!(
+        got.events[1].reason.as_deref(),
+        Some("550 5.1.1 mailbox unavailable")
+    );
+    assert_eq!(got.events[2].event_name, MessageEventKind::Unknown);
+
+    let body = String::from(r#"{"errors": [{"field": null, "message": "access forbidden"}]}"#);
+    match parse_activity(403, body) {
+        Err(SendgridError(SendgridErrorKind::MissingScope(scope), _)) => {
+            assert_eq!(scope, "messages.read")
+        }
+        other => panic!("expected a missing scope error, got {:?}", other),
+    }
+}
@@ -1,8 +1,7 @@
+use std::error::Error;
+use std::fmt;
 use std::io;
 
-use reqwest;
-use serde_json;
-
 error_chain! {
     types {
         SendgridError, SendgridErrorKind, SendgridResultExt, SendgridResult;
@@ -12,6 +11,8 @@ error_chain! {
         Io(io::Error);
         JSONDecode(serde_json::Error);
         ReqwestError(reqwest::Error);
+        InvalidHeader(reqwest::header::InvalidHeaderValue);
+        InvalidHeaderName(reqwest::header::InvalidHeaderName);
     }
 
     errors {
@@ -19,5 +20,339 @@ error_chain! {
             description("invalid filename")
             display("could not UTF-8 decode this filename")
         }
+
+        InvalidBase64(filename: String) {
+            description("invalid base64 attachment content")
+            display("the content of attachment {} is not base64", filename)
+        }
+
+        MissingBody {
+            description("the message has no body")
+            display("at least one of text or html must be set on the message")
+        }
+
+        MissingApiKey(var: String) {
+            description("no API key was found in the environment")
+            display("the {} environment variable must be set to a SendGrid API key", var)
+        }
+
+        MissingSubject {
+            description("the message has no subject")
+            display("the message needs a subject or a template that provides one")
+        }
+
+        InvalidEmail(address: String) {
+            description("invalid email address")
+            display("{} is not a valid email address", address)
+        }
+
+        NoRecipients {
+            description("the message has no recipients")
+            display("the message needs at least one to, cc or bcc address")
+        }
+
+        SubstitutionMismatch(tag: String, values: usize, recipients: usize) {
+            description("a substitution doesn't have one value per recipient")
+            display(
+                "substitution {} has {} values but the message has {} recipients",
+                tag,
+                values,
+                recipients
+            )
+        }
+
+        InvalidUnsubscribe(target: String) {
+            description("invalid unsubscribe link")
+            display("{} is not an https URL or a mailto address to unsubscribe with", target)
+        }
+
+        ReservedHeader(header: String) {
+            description("the header is reserved by SendGrid")
+            display(
+                "{} is reserved by SendGrid and can't be set as a custom header; the reserved \
+                 headers are {}",
+                header,
+                crate::mail::RESERVED_HEADERS.join(", ")
+            )
+        }
+
+        PayloadTooLarge(size: usize, limit: usize) {
+            description("the request is too large")
+            display("the request is {} bytes, but SendGrid accepts at most {}", size, limit)
+        }
+
+        AttachmentTooLarge(filename: String, size: usize, limit: usize) {
+            description("an attachment is too large")
+            display(
+                "attachment {} is {} bytes once encoded, but the limit is {}",
+                filename,
+                size,
+                limit
+            )
+        }
+
+        TooManyRecipients(count: usize, limit: usize) {
+            description("the message has too many recipients")
+            display("the message has {} recipients, but SendGrid accepts at most {}", count, limit)
+        }
+
+        TooManyAttachments(count: usize, limit: usize) {
+            description("the message has too many attachments")
+            display("the message has {} attachments, but the limit is {}", count, limit)
+        }
+
+        Timeout {
+            description("the request timed out")
+            display("the request to SendGrid timed out")
+        }
+
+        RetriesExhausted(attempts: u32) {
+            description("the request failed after retrying")
+            display("the request to SendGrid failed after {} attempts", attempts)
+        }
+
+        ScheduledTooFar(send_at: u64, latest: u64) {
+            description("the message is scheduled too far in the future")
+            display("send_at {} is later than {}, 72 hours from now", send_at, latest)
+        }
+
+        BatchNotScheduled(batch_id: String) {
+            description("the batch has no scheduled sends left")
+            display("batch {} has already been sent", batch_id)
+        }
+
+        InvalidMultipart(reason: String) {
+            description("invalid multipart body")
+            display("could not parse the multipart body: {}", reason)
+        }
+
+        Unauthorized {
+            description("SendGrid rejected the API key")
+            display("SendGrid rejected the API key; check that it is set and hasn't been revoked")
+        }
+
+        Forbidden {
+            description("the API key isn't allowed to send")
+            display(
+                "the API key isn't allowed to send; check that it has the mail.send scope and \
+                 that the from address is a verified sender"
+            )
+        }
+
+        MissingScope(scope: String) {
+            description("the API key doesn't have the required scope")
+            display("the API key needs the {} scope for this request", scope)
+        }
+
+        Api(status: u16, errors: Vec<ApiError>, body: String) {
+            description("the SendGrid API returned an error")
+            display(
+                "SendGrid API error {}{}: {}",
+                status,
+                status_hint(*status),
+                errors.iter().map(ApiError::to_string).collect::<Vec<_>>().join("; ")
+            )
+        }
+    }
+}
+
+impl SendgridError {
+    /// Whether this error is of the given kind, such as `SendgridErrorKind::MissingBody`. Only
+    /// the error itself is checked, not the errors that caused it.
+    pub fn is_kind(&self, kind: &SendgridErrorKind) -> bool {
+        self.kind() == kind
+    }
+
+    /// The HTTP status code SendGrid responded with, if this error was caused by an unsuccessful
+    /// response. This looks through the chain of causes, so it is also set when an API error has
+    /// been wrapped in a more specific kind such as `RetriesExhausted`. A 401 means the API key
+    /// is wrong or revoked, and a 403 means it doesn't have the scope the request needs.
+    pub fn status(&self) -> Option<u16> {
+        let mut next: Option<&(dyn Error + 'static)> = Some(self);
+        while let Some(err) = next {
+            if let Some(err) = err.downcast_ref::<SendgridError>() {
+                match *err.kind() {
+                    SendgridErrorKind::Api(status, _, _) => return Some(status),
+                    SendgridErrorKind::ReqwestError(ref err) => {
+                        return err.status().map(|s| s.as_u16())
+                    }
+                    _ => {
+                        next = err
+                            .1
+                            .next_error
+                            .as_deref()
+                            .map(|e| e as &(dyn Error + 'static))
+                    }
+                }
+            } else if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+                return err.status().map(|s| s.as_u16());
+            } else {
+                next = err.source();
+            }
+        }
+        None
+    }
+}
+
+/// Kinds defined by this crate compare equal when their fields are equal, so tests can check
+/// which error was returned. Kinds that wrap an error from another crate never compare equal,
+/// since those errors can't be compared.
+impl PartialEq for SendgridErrorKind {
+    fn eq(&self, other: &SendgridErrorKind) -> bool {
+        use self::SendgridErrorKind::*;
+
+        match (self, other) {
+            (InvalidFilename, InvalidFilename)
+            | (MissingBody, MissingBody)
+            | (MissingSubject, MissingSubject)
+            | (NoRecipients, NoRecipients)
+            | (Unauthorized, Unauthorized)
+            | (Forbidden, Forbidden)
+            | (Timeout, Timeout) => true,
+            (Msg(a), Msg(b))
+            | (MissingApiKey(a), MissingApiKey(b))
+            | (InvalidEmail(a), InvalidEmail(b))
+            | (InvalidBase64(a), InvalidBase64(b))
+            | (InvalidUnsubscribe(a), InvalidUnsubscribe(b)) => a == b,
+            (SubstitutionMismatch(a, b, c), SubstitutionMismatch(x, y, z)) => {
+                (a, b, c) == (x, y, z)
+            }
+            (ReservedHeader(a), ReservedHeader(b)) => a == b,
+            (PayloadTooLarge(a, b), PayloadTooLarge(x, y)) => (a, b) == (x, y),
+            (AttachmentTooLarge(a, b, c), AttachmentTooLarge(x, y, z)) => (a, b, c) == (x, y, z),
+            (TooManyRecipients(a, b), TooManyRecipients(x, y)) => (a, b) == (x, y),
+            (TooManyAttachments(a, b), TooManyAttachments(x, y)) => (a, b) == (x, y),
+            (ScheduledTooFar(a, b), ScheduledTooFar(x, y)) => (a, b) == (x, y),
+            (RetriesExhausted(a), RetriesExhausted(b)) => a == b,
+            (BatchNotScheduled(a), BatchNotScheduled(b)) => a == b,
+            (InvalidMultipart(a), InvalidMultipart(b)) => a == b,
+            (MissingScope(a), MissingScope(b)) => a == b,
+            (Api(a, b, c), Api(x, y, z)) => (a, b, c) == (x, y, z),
+            _ => false,
+        }
+    }
+}
+
+// A short explanation of the status codes that are usually caused by the client's setup rather
+// than the request itself, to go alongside the errors SendGrid reported.
+fn status_hint(status: u16) -> &'static str {
+    match status {
+        401 => " (the API key is missing, invalid or revoked)",
+        403 => " (the API key doesn't have permission for this request)",
+        404 => " (not found)",
+        413 => " (the request is too large)",
+        429 => " (rate limited, try again later)",
+        500..=599 => " (SendGrid is having problems, try again later)",
+        _ => "",
+    }
+}
+
+/// A single entry from the `errors` array SendGrid returns when it rejects a request.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(from = "RawApiError")]
+pub struct ApiError {
+    /// A description of what was wrong with the request.
+    pub message: String,
+    /// The field of the request that caused the error, if SendGrid identified one.
+    pub field: Option<String>,
+    /// A link to documentation about the error, if SendGrid included one.
+    pub help: Option<String>,
+}
+
+// The V2 API reports errors as plain strings while V3 uses objects, so accept either.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawApiError {
+    Message(String),
+    Detailed {
+        message: String,
+        field: Option<String>,
+        help: Option<String>,
+    },
+}
+
+impl From<RawApiError> for ApiError {
+    fn from(raw: RawApiError) -> ApiError {
+        match raw {
+            RawApiError::Message(message) => ApiError {
+                message,
+                field: None,
+                help: None,
+            },
+            RawApiError::Detailed {
+                message,
+                field,
+                help,
+            } => ApiError {
+                message,
+                field,
+                help,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref field) = self.field {
+            write!(f, "field '{}' - ", field)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(ref help) = self.help {
+            write!(f, " (see {})", help)?;
+        }
+        Ok(())
     }
 }
+
+#[test]
+fn api_error_display() {
+    let errors = vec![
+        ApiError {
+            message: "Does not contain a valid address".to_string(),
+            field: Some("from.email".to_string()),
+            help: Some("http://sendgrid.com/docs".to_string()),
+        },
+        ApiError {
+            message: "Bad key".to_string(),
+            field: None,
+            help: None,
+        },
+    ];
+    let err: SendgridError = SendgridErrorKind::Api(400, errors, String::new()).into();
+    assert_eq!(
+        err.to_string(),
+        "SendGrid API error 400: field 'from.email' - Does not contain a valid address \
+         (see http://sendgrid.com/docs); Bad key"
+    );
+}
+
+#[test]
+fn status_codes() {
+    let err: SendgridError = SendgridErrorKind::Api(401, Vec::new(), String::new()).into();
+    assert_eq!(err.status(), Some(401));
+    assert_eq!(
+        err.to_string(),
+        "SendGrid API error 401 (the API key is missing, invalid or revoked): "
+    );
+
+    let err = SendgridError::with_chain(err, SendgridErrorKind::RetriesExhausted(3));
+    assert_eq!(err.status(), Some(401));
+
+    let err: SendgridError = SendgridErrorKind::Timeout.into();
+    assert_eq!(err.status(), None);
+}
+
+#[test]
+fn compare_kinds() {
+    let err: SendgridError = SendgridErrorKind::InvalidEmail(String::from("nope")).into();
+    assert!(err.is_kind(&SendgridErrorKind::InvalidEmail(String::from("nope"))));
+    assert!(!err.is_kind(&SendgridErrorKind::InvalidEmail(String::from("other"))));
+    assert!(!err.is_kind(&SendgridErrorKind::MissingBody));
+    assert_eq!(
+        SendgridErrorKind::MissingBody,
+        SendgridErrorKind::MissingBody
+    );
+    let io = || SendgridErrorKind::Io(std::io::ErrorKind::Other.into());
+    assert_ne!(io(), io());
+}
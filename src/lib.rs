@@ -4,13 +4,25 @@ extern crate error_chain;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(feature = "chrono")]
+extern crate chrono;
 extern crate data_encoding;
+#[cfg(feature = "p256")]
+extern crate p256;
 extern crate reqwest;
 extern crate serde;
 extern crate serde_json;
 extern crate url;
 
+pub mod activity;
 pub mod errors;
 pub mod mail;
+pub mod mail_settings;
+pub mod senders;
 pub mod sg_client;
+pub mod smtpapi;
+pub mod stats;
+pub mod suppression;
+pub mod transport;
 pub mod v3;
+pub mod validation;
@@ -1,31 +1,303 @@
-use errors::{SendgridErrorKind, SendgridResult};
+use crate::errors::{SendgridError, SendgridErrorKind, SendgridResult};
+use crate::smtpapi::SmtpApiHeader;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read};
 use std::path::Path;
 
-use serde_json;
+use data_encoding::{BASE64, BASE64URL_NOPAD, BASE64_NOPAD};
 
-#[derive(Debug)]
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+/// A recipient of a message, made up of an address and an optional display name. Keeping the two
+/// together means a name can never be sent alongside the wrong address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    pub address: String,
+    pub name: Option<String>,
+}
+
+impl Destination {
+    /// Construct a destination with an address and no name.
+    pub fn new<T: Into<String>>(address: T) -> Destination {
+        Destination {
+            address: address.into(),
+            name: None,
+        }
+    }
+
+    /// Construct a destination with both an address and a display name.
+    pub fn with_name<T: Into<String>, U: Into<String>>(address: T, name: U) -> Destination {
+        Destination {
+            address: address.into(),
+            name: Some(name.into()),
+        }
+    }
+
+    /// Parse a mailbox such as `Alice <alice@example.com>` or `"Doe, Jane" <jane@example.com>`
+    /// into an address and a name. A bare address, with or without angle brackets, gives a
+    /// destination with no name. Anything else returns `SendgridErrorKind::InvalidEmail`.
+    pub fn parse(mailbox: &str) -> SendgridResult<Destination> {
+        let invalid = || SendgridErrorKind::InvalidEmail(mailbox.to_string()).into();
+        let mailbox = mailbox.trim();
+
+        let bracketed = mailbox
+            .strip_suffix('>')
+            .and_then(|s| s.rfind('<').map(|i| (&s[i + 1..], s[..i].trim())));
+        let (address, name) = bracketed.unwrap_or((mailbox, ""));
+        if !is_valid_address(address) {
+            return Err(invalid());
+        }
+
+        let name = match name.strip_prefix('"') {
+            Some(quoted) => {
+                let quoted = quoted.strip_suffix('"').ok_or_else(invalid)?;
+                let mut name = String::new();
+                let mut chars = quoted.chars();
+                while let Some(c) = chars.next() {
+                    name.extend(if c == '\\' { chars.next() } else { Some(c) });
+                }
+                name
+            }
+            None => name.to_string(),
+        };
+        if name.is_empty() {
+            Ok(Destination::new(address))
+        } else {
+            Ok(Destination::with_name(address, name))
+        }
+    }
+}
+
+impl fmt::Display for Destination {
+    /// Formats the destination as an RFC 5322 mailbox, such as `Alice <alice@example.com>`. The
+    /// name is quoted if it contains characters that have a special meaning in a mailbox.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            None => write!(f, "{}", self.address),
+            Some(ref name) if name.chars().any(|c| "()<>[]:;@\\,.\"".contains(c)) => {
+                let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "\"{}\" <{}>", escaped, self.address)
+            }
+            Some(ref name) => write!(f, "{} <{}>", name, self.address),
+        }
+    }
+}
+
+/// Parses the string like `Destination::parse`, so `"Alice <alice@example.com>"` becomes an
+/// address with a name. Strings that don't parse are kept whole as the address, since `From`
+/// can't fail; use `Destination::parse` to reject them instead.
+impl<'a> From<&'a str> for Destination {
+    fn from(address: &'a str) -> Destination {
+        Destination::parse(address).unwrap_or_else(|_| Destination::new(address))
+    }
+}
+
+/// Parses the string like the conversion from `&str`.
+impl From<String> for Destination {
+    fn from(address: String) -> Destination {
+        Destination::parse(&address).unwrap_or_else(|_| Destination::new(address))
+    }
+}
+
+impl<T: Into<String>, U: Into<String>> From<(T, U)> for Destination {
+    fn from((address, name): (T, U)) -> Destination {
+        Destination::with_name(address, name)
+    }
+}
+
+// A basic check of address syntax, which catches common mistakes such as a missing "@" without
+// attempting to implement all of RFC 5322.
+pub(crate) fn is_valid_address(address: &str) -> bool {
+    let mut parts = address.splitn(2, '@');
+    let (local, domain) = match (parts.next(), parts.next()) {
+        (Some(local), Some(domain)) => (local, domain),
+        _ => return false,
+    };
+
+    let valid_local = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"()<>[]\\,;:\"".contains(c));
+    let valid_domain = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    valid_local && valid_domain
+}
+
+/// The headers SendGrid doesn't allow to be set as custom headers, because it sets them itself
+/// or they are set from other fields of the message.
+pub static RESERVED_HEADERS: &[&str] = &[
+    "x-sg-id",
+    "x-sg-eid",
+    "received",
+    "dkim-signature",
+    "Content-Type",
+    "Content-Transfer-Encoding",
+    "To",
+    "From",
+    "Subject",
+    "Reply-To",
+    "CC",
+    "BCC",
+];
+
+// The form of an address used to spot duplicates. Domains are case insensitive, but the local
+// part may not be, so it is left alone.
+fn address_key(address: &str) -> String {
+    match address.rfind('@') {
+        Some(at) => format!("{}{}", &address[..at], address[at..].to_lowercase()),
+        None => address.to_string(),
+    }
+}
+
+// Parse a comma separated list of mailboxes. Commas inside a quoted name don't separate entries,
+// and empty entries, such as from a trailing comma, are skipped. Nothing is returned unless every
+// entry parses, so a bad entry can't leave a list half added.
+fn parse_csv(list: &str) -> SendgridResult<Vec<Destination>> {
+    let mut entries = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in list.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                entries.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&list[start..]);
+
+    entries
+        .into_iter()
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(Destination::parse)
+        .collect()
+}
+
+// Base64 encode everything from a reader a chunk at a time. Chunks are a multiple of three
+// bytes, so encoding them one after another gives the same result as encoding all of the data.
+fn encode_reader<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut encoded = String::new();
+    let mut chunk = vec![0; 3 * 16 * 1024];
+    loop {
+        let mut filled = 0;
+        while filled < chunk.len() {
+            match reader.read(&mut chunk[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        BASE64.encode_append(&chunk[..filled], &mut encoded);
+        if filled < chunk.len() {
+            return Ok(encoded);
+        }
+    }
+}
+
+// Convert already encoded content to the standard, padded base64 SendGrid expects. Line breaks
+// and padding are dropped, URL-safe content is recognised by its alphabet, and the content is
+// decoded and encoded again so anything that isn't base64 is caught before sending.
+pub(crate) fn normalize_base64(filename: &str, encoded: &str) -> SendgridResult<String> {
+    let stripped: String = encoded
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && *c != '=')
+        .collect();
+    let encoding = if stripped.contains(['-', '_']) {
+        &BASE64URL_NOPAD
+    } else {
+        &BASE64_NOPAD
+    };
+    match encoding.decode(stripped.as_bytes()) {
+        Ok(data) => Ok(BASE64.encode(&data)),
+        Err(e) => Err(SendgridError::with_chain(
+            e,
+            SendgridErrorKind::InvalidBase64(filename.to_string()),
+        )),
+    }
+}
+
+/// A file attached to a message. Attachments are stored by filename on the `Mail` struct.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    /// The base64 encoded contents of the file.
+    pub content: String,
+    /// The MIME type of the file, such as "application/pdf".
+    pub content_type: String,
+    /// Whether the file is shown as an attachment or within the HTML content.
+    pub disposition: Disposition,
+}
+
+/// How an attachment is presented to the recipient. This is used by both the V2 and V3 APIs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Disposition {
+    /// A file the recipient can download. This is the default.
+    #[default]
+    Attachment,
+    /// A file shown within the HTML content, which refers to it with a `cid:` URL.
+    Inline {
+        /// The ID the HTML content uses to refer to the file.
+        content_id: String,
+    },
+}
+
+/// How important a message is, which some mail clients use to flag or sort it. See
+/// `Mail::set_priority`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Priority {
+    /// The message is flagged as important.
+    High,
+    /// No priority headers are sent, which clients treat as normal. This is the default.
+    #[default]
+    Normal,
+    /// The message is marked as less important than usual.
+    Low,
+}
+
+// The headers mail clients read the priority of a message from.
+const PRIORITY_HEADERS: [&str; 3] = ["X-Priority", "X-MSMail-Priority", "Importance"];
+
+#[derive(Clone, Debug)]
 /// This is a representation of a valid SendGrid message. It has support for
 /// all of the fields in the V2 API.
 pub struct Mail {
-    pub to: Vec<String>,
-    pub to_names: Vec<String>,
-    pub cc: Vec<String>,
-    pub bcc: Vec<String>,
+    pub to: Vec<Destination>,
+    pub cc: Vec<Destination>,
+    pub bcc: Vec<Destination>,
     pub from: String,
     pub subject: String,
     pub html: String,
     pub text: String,
     pub from_name: String,
-    pub reply_to: String,
+    pub reply_to: Option<Destination>,
     pub date: String,
-    pub attachments: HashMap<String, String>,
+    pub attachments: HashMap<String, Attachment>,
     pub content: HashMap<String, String>,
     pub headers: HashMap<String, String>,
     pub x_smtpapi: String,
+    pub smtpapi: SmtpApiHeader,
+    pub idempotency_key: Option<String>,
+    pub request_headers: HashMap<String, String>,
+}
+
+impl Default for Mail {
+    fn default() -> Mail {
+        Mail::new()
+    }
 }
 
 impl Mail {
@@ -34,7 +306,6 @@ impl Mail {
     pub fn new() -> Mail {
         Mail {
             to: Vec::new(),
-            to_names: Vec::new(),
             cc: Vec::new(),
             bcc: Vec::new(),
             from: String::new(),
@@ -42,25 +313,140 @@ impl Mail {
             html: String::new(),
             text: String::new(),
             from_name: String::new(),
-            reply_to: String::new(),
+            reply_to: None,
             date: String::new(),
             attachments: HashMap::new(),
             content: HashMap::new(),
             headers: HashMap::new(),
             x_smtpapi: String::new(),
+            smtpapi: SmtpApiHeader::new(),
+            idempotency_key: None,
+            request_headers: HashMap::new(),
         }
     }
 
-    /// Adds a CC recipient to the Mail struct.
-    pub fn add_cc<T: Into<String>>(&mut self, cc_addr: T) {
+    /// Returns a new plain text message with a single recipient. Everything but the body can be
+    /// changed or added to afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sendgrid::mail::Mail;
+    /// let message = Mail::text(
+    ///     "alice@example.com",
+    ///     "Your order has shipped",
+    ///     "orders@example.com",
+    ///     "It should arrive on Tuesday.",
+    /// );
+    /// ```
+    pub fn text<D, S, F, B>(to: D, subject: S, from: F, body: B) -> Mail
+    where
+        D: Into<Destination>,
+        S: Into<String>,
+        F: Into<String>,
+        B: Into<String>,
+    {
+        let mut mail = Mail::new();
+        mail.add_to(to);
+        mail.add_subject(subject);
+        mail.add_from(from);
+        mail.add_text(body);
+        mail
+    }
+
+    /// Returns a new HTML message with a single recipient, like `Mail::text`.
+    pub fn html<D, S, F, B>(to: D, subject: S, from: F, body: B) -> Mail
+    where
+        D: Into<Destination>,
+        S: Into<String>,
+        F: Into<String>,
+        B: Into<String>,
+    {
+        let mut mail = Mail::new();
+        mail.add_to(to);
+        mail.add_subject(subject);
+        mail.add_from(from);
+        mail.add_html(body);
+        mail
+    }
+
+    /// Adds a CC recipient to the Mail struct. This can be a bare address or a `Destination`
+    /// with a name.
+    pub fn add_cc<T: Into<Destination>>(&mut self, cc_addr: T) {
         self.cc.push(cc_addr.into())
     }
 
-    /// Adds a to recipient to the Mail struct.
-    pub fn add_to<T: Into<String>>(&mut self, to_addr: T) {
+    /// Adds a to recipient to the Mail struct. This can be a bare address, an
+    /// `(address, name)` pair or a `Destination`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sendgrid::mail::Mail;
+    /// let mut message = Mail::new();
+    /// message.add_to("alice@example.com");
+    /// message.add_to(("bob@example.com", "Bob"));
+    /// ```
+    pub fn add_to<T: Into<Destination>>(&mut self, to_addr: T) {
         self.to.push(to_addr.into())
     }
 
+    /// Adds several to recipients at once, in order. Each can be anything accepted by `add_to`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sendgrid::mail::Mail;
+    /// let mut message = Mail::new();
+    /// message.add_recipients(vec!["alice@example.com", "bob@example.com"]);
+    /// ```
+    pub fn add_recipients<I>(&mut self, dests: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Destination>,
+    {
+        self.to.extend(dests.into_iter().map(Into::into))
+    }
+
+    /// Adds several CC recipients at once, in order.
+    pub fn add_cc_recipients<I>(&mut self, dests: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Destination>,
+    {
+        self.cc.extend(dests.into_iter().map(Into::into))
+    }
+
+    /// Adds several BCC recipients at once, in order.
+    pub fn add_bcc_recipients<I>(&mut self, dests: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Destination>,
+    {
+        self.bcc.extend(dests.into_iter().map(Into::into))
+    }
+
+    /// Adds to recipients from a comma separated list, such as one read from configuration.
+    /// Each entry is trimmed and parsed with `Destination::parse`, so it can be a bare address
+    /// or have a name, like `a@example.com, Bob <b@example.com>`. If any entry is malformed,
+    /// `SendgridErrorKind::InvalidEmail` is returned with that entry and none are added.
+    pub fn add_to_csv(&mut self, list: &str) -> SendgridResult<()> {
+        self.to.extend(parse_csv(list)?);
+        Ok(())
+    }
+
+    /// Adds CC recipients from a comma separated list, like `add_to_csv`.
+    pub fn add_cc_csv(&mut self, list: &str) -> SendgridResult<()> {
+        self.cc.extend(parse_csv(list)?);
+        Ok(())
+    }
+
+    /// Adds BCC recipients from a comma separated list, like `add_to_csv`.
+    pub fn add_bcc_csv(&mut self, list: &str) -> SendgridResult<()> {
+        self.bcc.extend(parse_csv(list)?);
+        Ok(())
+    }
+
     /// Set the from address for the Mail struct. This can be changed, but there
     /// is only one from address per message.
     pub fn add_from<T: Into<String>>(&mut self, from_addr: T) {
@@ -72,24 +458,38 @@ impl Mail {
         self.subject = subject.into()
     }
 
-    /// This function sets the HTML content for the message.
+    /// This function sets the HTML content for the message. A message must have HTML content,
+    /// text content, or both before it can be sent.
     pub fn add_html<T: Into<String>>(&mut self, html: T) {
         self.html = html.into()
     }
 
-    /// Add a name for the "to" field in the message. The number of to names
-    /// must match the number of "to" addresses.
+    /// Add a name for the "to" field in the message. The name is given to the first "to"
+    /// address that doesn't have one yet, so names added in the same order as the addresses
+    /// line up with them. Passing a `Destination` to `add_to` is less error prone.
     pub fn add_to_name<T: Into<String>>(&mut self, to_name: T) {
-        self.to_names.push(to_name.into());
+        if let Some(to) = self.to.iter_mut().find(|to| to.name.is_none()) {
+            to.name = Some(to_name.into());
+        }
     }
 
-    /// Set the text content of the message.
+    /// Set the text content of the message. A message must have text content, HTML content, or
+    /// both before it can be sent.
     pub fn add_text<T: Into<String>>(&mut self, text: T) {
         self.text = text.into()
     }
 
-    /// Add a BCC address to the message.
-    pub fn add_bcc<T: Into<String>>(&mut self, bcc_addr: T) {
+    /// Set both the text and HTML content of the message, which is then sent as
+    /// multipart/alternative. Providing both is recommended, because messages with only HTML are
+    /// more likely to be filtered as spam.
+    pub fn body<T: Into<String>, U: Into<String>>(&mut self, text: T, html: U) {
+        self.add_text(text);
+        self.add_html(html);
+    }
+
+    /// Add a BCC address to the message. This can be a bare address or a `Destination` with a
+    /// name.
+    pub fn add_bcc<T: Into<Destination>>(&mut self, bcc_addr: T) {
         self.bcc.push(bcc_addr.into())
     }
 
@@ -99,17 +499,35 @@ impl Mail {
     }
 
     /// Set the reply to address for the message.
-    pub fn add_reply_to<T: Into<String>>(&mut self, reply_to: T) {
-        self.reply_to = reply_to.into()
+    ///
+    /// This can be a bare address or a `Destination` with a name, such as
+    /// `("support@example.com", "Support Team")`. The V2 API has a single reply to field, so a
+    /// name is sent in the mailbox form `Support Team <support@example.com>`.
+    pub fn add_reply_to<T: Into<Destination>>(&mut self, reply_to: T) {
+        self.reply_to = Some(reply_to.into())
+    }
+
+    /// Set the reply to address for the message without a name.
+    pub fn add_reply_to_address<T: Into<String>>(&mut self, reply_to: T) {
+        self.reply_to = Some(Destination::new(reply_to))
     }
 
     /// Set the date for the message. This must be a valid RFC 822 timestamp.
-    pub fn add_date(&mut self, date: String) {
-        self.date = date
+    pub fn add_date<T: Into<String>>(&mut self, date: T) {
+        self.date = date.into()
+    }
+
+    /// Set the date for the message from a chrono timestamp, which is formatted as RFC 2822.
+    /// This requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn add_date_chrono(&mut self, date: DateTime<Utc>) {
+        self.date = date.to_rfc2822()
     }
 
     /// Add an attachment for the message. You can pass the name of a file as a
-    /// path on the file system.
+    /// path on the file system. Only the last component of the path is used as the name of the
+    /// attachment, and its content type is "application/octet-stream". Use
+    /// `add_attachment_bytes` to choose the content type.
     ///
     /// # Examples
     ///
@@ -118,28 +536,270 @@ impl Mail {
     /// message.add_attachment("/path/to/file/contents.txt");
     /// ```
     pub fn add_attachment<P: AsRef<Path>>(&mut self, path: P) -> SendgridResult<()> {
-        let mut file = File::open(&path)?;
-        let mut data = String::new();
-        file.read_to_string(&mut data)?;
+        let name = path
+            .as_ref()
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(SendgridErrorKind::InvalidFilename)?;
+        let file = File::open(&path)?;
 
-        if let Some(name) = path.as_ref().to_str() {
-            self.attachments.insert(String::from(name), data);
-        } else {
-            return Err(SendgridErrorKind::InvalidFilename.into());
-        }
+        self.add_attachment_reader(name, "application/octet-stream", file)
+    }
+
+    /// Add an attachment for the message by reading everything from a reader, such as a
+    /// decompressor or a network stream. There is no limit on how much is read, so only use
+    /// this with readers of a known, reasonable size.
+    ///
+    /// The data is base64 encoded as it is read, so only the encoded attachment, which is a
+    /// third larger than the original, is held in memory rather than both. Sending still needs
+    /// the whole request body in memory alongside the message, since it is encoded before it
+    /// is sent, so a message uses roughly three times the size of its attachments at its peak.
+    pub fn add_attachment_reader<R: Read>(
+        &mut self,
+        filename: &str,
+        content_type: &str,
+        reader: R,
+    ) -> SendgridResult<()> {
+        let attachment = Attachment {
+            content: encode_reader(reader)?,
+            content_type: content_type.to_string(),
+            disposition: Disposition::Attachment,
+        };
+        self.attachments.insert(filename.to_string(), attachment);
 
         Ok(())
     }
 
-    /// Add content for inline images in the message.
-    pub fn add_content(&mut self, id: &str, value: &str) {
-        self.content.insert(id.to_string(), value.to_string());
+    /// Add an attachment for the message from data in memory. The data is base64 encoded and
+    /// stored under the given filename, replacing any attachment that already has that name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sendgrid::mail::Mail;
+    /// let mut message = Mail::new();
+    /// message.add_attachment_bytes("report.csv", "text/csv", b"id,total\n1,42\n");
+    /// ```
+    pub fn add_attachment_bytes(&mut self, filename: &str, content_type: &str, data: &[u8]) {
+        self.add_attachment_with_disposition(filename, content_type, data, Disposition::Attachment)
+    }
+
+    /// Add an attachment whose content is already base64 encoded, without encoding it again.
+    /// SendGrid requires the standard alphabet with padding, so URL-safe or unpadded content is
+    /// converted, and line breaks are removed. Content that isn't base64 in either alphabet is
+    /// rejected with `SendgridErrorKind::InvalidBase64`.
+    pub fn add_attachment_base64(
+        &mut self,
+        filename: &str,
+        content_type: &str,
+        encoded: &str,
+    ) -> SendgridResult<()> {
+        let attachment = Attachment {
+            content: normalize_base64(filename, encoded)?,
+            content_type: content_type.to_string(),
+            disposition: Disposition::Attachment,
+        };
+        self.attachments.insert(filename.to_string(), attachment);
+
+        Ok(())
+    }
+
+    /// Add an attachment from bytes in memory, choosing how it is presented to the recipient.
+    /// Inline attachments have their filename mapped to the content ID when the message is sent,
+    /// which is how the V2 API identifies them.
+    pub fn add_attachment_with_disposition(
+        &mut self,
+        filename: &str,
+        content_type: &str,
+        data: &[u8],
+        disposition: Disposition,
+    ) {
+        self.attachments.insert(
+            filename.to_string(),
+            Attachment {
+                content: BASE64.encode(data),
+                content_type: content_type.to_string(),
+                disposition,
+            },
+        );
+    }
+
+    /// Add a detached PGP signature of the message, as `signature.asc` with the
+    /// `application/pgp-signature` type. This only attaches the signature: SendGrid builds the
+    /// MIME structure of the message itself, so it can't be sent as multipart/signed, and mail
+    /// clients will show it as an attachment rather than verifying it. The signature has to
+    /// cover the content as you built it, since SendGrid may rewrite the body, such as for click
+    /// tracking, so tracking should be turned off for signed messages.
+    pub fn add_signature_part(&mut self, signature: &[u8]) {
+        self.add_attachment_bytes("signature.asc", "application/pgp-signature", signature)
+    }
+
+    /// Add an image that can be embedded in the HTML content of the message by referring to its
+    /// content ID with a `cid:` URL. This is the same as adding an attachment with an inline
+    /// disposition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sendgrid::mail::Mail;
+    /// # let logo_png: &[u8] = &[];
+    /// let mut message = Mail::new();
+    /// message.add_html("<img src=\"cid:logo\"> Welcome aboard!");
+    /// message.add_inline_image("logo", "logo.png", "image/png", logo_png);
+    /// ```
+    pub fn add_inline_image(&mut self, cid: &str, filename: &str, content_type: &str, data: &[u8]) {
+        let disposition = Disposition::Inline {
+            content_id: cid.to_string(),
+        };
+        self.add_attachment_with_disposition(filename, content_type, data, disposition)
+    }
+
+    /// Add content for inline images in the message. The id is the filename of an attachment
+    /// and the value is the content ID used to refer to it from the HTML content. Despite the
+    /// name, the V2 API has no way to add body parts of other MIME types, such as a calendar
+    /// invite; use `SGMailV3::add_content_part` for those.
+    pub fn add_content<T: Into<String>, U: Into<String>>(&mut self, id: T, value: U) {
+        self.content.insert(id.into(), value.into());
     }
 
     /// Add a custom header for the message. These are usually prefixed with
     /// 'X' or 'x' per the RFC specifications.
-    pub fn add_header(&mut self, header: &str, value: &str) {
-        self.headers.insert(header.to_string(), value.to_string());
+    pub fn add_header<T: Into<String>, U: Into<String>>(&mut self, header: T, value: U) {
+        self.headers.insert(header.into(), value.into());
+    }
+
+    /// Add a custom header for the message, like `add_header`, but first check that it isn't one
+    /// of the `RESERVED_HEADERS`. Reserved headers are rejected by SendGrid or conflict with
+    /// other fields, so this returns `SendgridErrorKind::ReservedHeader` for them instead. Use
+    /// this for headers that come from untrusted input.
+    pub fn try_add_header<T: Into<String>, U: Into<String>>(
+        &mut self,
+        header: T,
+        value: U,
+    ) -> SendgridResult<()> {
+        let header = header.into();
+        if RESERVED_HEADERS
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(header.trim()))
+        {
+            return Err(SendgridErrorKind::ReservedHeader(header).into());
+        }
+        self.add_header(header, value);
+        Ok(())
+    }
+
+    /// Set a key that identifies this message, which is sent in an `Idempotency-Key` header with
+    /// every attempt to send it, including retries. SendGrid doesn't act on the header itself,
+    /// but a proxy or other deduplication layer in front of it can use the key to drop a retry
+    /// of a request that had in fact been delivered.
+    pub fn set_idempotency_key<T: Into<String>>(&mut self, key: T) {
+        self.idempotency_key = Some(key.into());
+    }
+
+    /// Add a header to the HTTP request that sends the message, rather than to the message
+    /// itself like `add_header`. This can carry tracing information, for example. It can't
+    /// replace the headers the client sets, such as `Authorization` or `Content-Type`.
+    pub fn add_request_header<T: Into<String>, U: Into<String>>(&mut self, name: T, value: U) {
+        self.request_headers.insert(name.into(), value.into());
+    }
+
+    /// Set an `X-Correlation-Id` header on the HTTP request that sends the message, so the send
+    /// can be traced across logs.
+    pub fn set_correlation_id<T: Into<String>>(&mut self, id: T) {
+        self.add_request_header("X-Correlation-Id", id)
+    }
+
+    /// Set the priority of the message by setting all three headers clients look for:
+    /// `X-Priority`, which Outlook, Apple Mail and Thunderbird read, `Importance`, which Outlook
+    /// and Exchange read, and `X-MSMail-Priority`, which older versions of Outlook read. Gmail
+    /// and most webmail ignore all of them. `Priority::Normal` removes the headers.
+    pub fn set_priority(&mut self, priority: Priority) {
+        let values = match priority {
+            Priority::High => ["1 (Highest)", "High", "high"],
+            Priority::Normal => {
+                for header in &PRIORITY_HEADERS {
+                    self.headers.remove(*header);
+                }
+                return;
+            }
+            Priority::Low => ["5 (Lowest)", "Low", "low"],
+        };
+        for (header, value) in PRIORITY_HEADERS.iter().zip(&values) {
+            self.add_header(*header, *value);
+        }
+    }
+
+    /// Returns a copy of the message sent to the given recipient instead of the current to
+    /// addresses, so a message can be built once and sent to each recipient in turn. Everything
+    /// else is kept, including any cc and bcc addresses.
+    pub fn with_recipient<T: Into<Destination>>(&self, to: T) -> Mail {
+        let mut mail = self.clone();
+        mail.to = vec![to.into()];
+        mail
+    }
+
+    /// Iterate over every recipient of the message: the to addresses, then cc, then bcc. Each
+    /// address is kept with its name, so this can be used to log who a message is going to.
+    pub fn recipients(&self) -> impl Iterator<Item = &Destination> {
+        self.to.iter().chain(&self.cc).chain(&self.bcc)
+    }
+
+    /// Set the `List-Unsubscribe` header, which mail clients use to show an unsubscribe button,
+    /// to the given `https` URL or `mailto` address. For a URL, this also sets
+    /// `List-Unsubscribe-Post: List-Unsubscribe=One-Click`, which is what RFC 8058 one-click
+    /// unsubscribe and bulk sender requirements such as Gmail's expect. The URL must then
+    /// unsubscribe the recipient when it receives a POST, without asking them to confirm, so it
+    /// should identify the recipient itself. A `mailto` address doesn't support one-click
+    /// unsubscribe. Anything else returns `SendgridErrorKind::InvalidUnsubscribe`.
+    pub fn set_list_unsubscribe(&mut self, target: &str) -> SendgridResult<()> {
+        let one_click = match url::Url::parse(target) {
+            Ok(ref url) if url.scheme() == "https" && url.host().is_some() => true,
+            Ok(ref url) if url.scheme() == "mailto" && is_valid_address(url.path()) => false,
+            _ => return Err(SendgridErrorKind::InvalidUnsubscribe(target.to_string()).into()),
+        };
+
+        self.add_header("List-Unsubscribe", format!("<{}>", target));
+        if one_click {
+            self.add_header("List-Unsubscribe-Post", "List-Unsubscribe=One-Click");
+        } else {
+            self.headers.remove("List-Unsubscribe-Post");
+        }
+        Ok(())
+    }
+
+    /// Remove recipients that appear more than once, so each address gets a single copy. An
+    /// address in to is removed from cc and bcc, and one in cc is removed from bcc. Only the
+    /// first occurrence is kept within each list. Domains are compared case insensitively. This
+    /// isn't done when sending, since some senders duplicate recipients on purpose.
+    pub fn dedupe_recipients(&mut self) {
+        let mut seen = HashSet::new();
+        for dests in &mut [&mut self.to, &mut self.cc, &mut self.bcc] {
+            dests.retain(|dest| seen.insert(address_key(&dest.address)));
+        }
+    }
+
+    /// Check the message before sending it, so that mistakes are caught without a round trip to
+    /// SendGrid. This returns `SendgridErrorKind::NoRecipients` if there are no to, cc or bcc
+    /// addresses, or `SendgridErrorKind::InvalidEmail` naming the first address that doesn't
+    /// look like an email address. Sending doesn't call this, so it is only run if you ask for
+    /// it.
+    pub fn validate(&self) -> SendgridResult<()> {
+        if self.to.is_empty() && self.cc.is_empty() && self.bcc.is_empty() {
+            return Err(SendgridErrorKind::NoRecipients.into());
+        }
+
+        let addresses = self
+            .recipients()
+            .map(|dest| dest.address.as_str())
+            .chain(Some(self.from.as_str()))
+            .chain(self.reply_to.as_ref().map(|dest| dest.address.as_str()));
+        for address in addresses {
+            if !is_valid_address(address) {
+                return Err(SendgridErrorKind::InvalidEmail(address.to_string()).into());
+            }
+        }
+
+        Ok(())
     }
 
     /// Used internally for string encoding. Not needed for message building.
@@ -149,9 +809,442 @@ impl Mail {
     }
 
     /// Add an X-SMTPAPI string to the message. This can be done by using the
-    /// 'rustc_serialize' crate and JSON encoding a map or custom struct. Or
-    /// a regular String type can be escaped and used.
-    pub fn add_x_smtpapi(&mut self, x_smtpapi: String) {
-        self.x_smtpapi = x_smtpapi
+    /// 'serde_json' crate and JSON encoding a map or custom struct. Or
+    /// a regular String type can be escaped and used. A raw string set here takes precedence
+    /// over a header set with `set_smtpapi`.
+    pub fn add_x_smtpapi<T: Into<String>>(&mut self, x_smtpapi: T) {
+        self.x_smtpapi = x_smtpapi.into()
+    }
+
+    /// Set a typed X-SMTPAPI header on the message, which is encoded to JSON when it is sent.
+    pub fn set_smtpapi(&mut self, header: SmtpApiHeader) {
+        self.smtpapi = header
+    }
+
+    /// Add a substitution tag to the X-SMTPAPI header, so one message can be personalized for
+    /// each recipient. There must be one value for each "to" address, in the same order, or
+    /// sending the message fails with `SendgridErrorKind::SubstitutionMismatch`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sendgrid::mail::Mail;
+    /// let mut message = Mail::new();
+    /// message.add_to("alice@example.com");
+    /// message.add_to("bob@example.com");
+    /// message.add_text("Hello -name-!");
+    /// message.add_substitution("-name-", vec!["Alice", "Bob"]);
+    /// ```
+    pub fn add_substitution<S, I, T>(&mut self, tag: S, values: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.smtpapi.add_substitution(tag, values)
+    }
+
+    /// Tag the message with a category, so its statistics can be filtered in the SendGrid
+    /// dashboard. A message can have more than one category.
+    pub fn add_category<T: Into<String>>(&mut self, category: T) {
+        self.smtpapi.add_category(category)
+    }
+
+    /// Schedule the message to be delivered at a later time, given as a Unix timestamp.
+    /// SendGrid rejects messages scheduled more than 72 hours in the future.
+    pub fn set_send_at(&mut self, timestamp: i64) {
+        self.smtpapi.set_send_at(timestamp)
+    }
+
+    /// Schedule the message to be delivered at a later time. SendGrid rejects messages
+    /// scheduled more than 72 hours in the future. This requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn set_send_at_chrono(&mut self, time: DateTime<Utc>) {
+        self.set_send_at(time.timestamp())
+    }
+
+    /// Associate the message with an unsubscribe group, so recipients can opt out of just the
+    /// messages in that group. The group must already exist in the SendGrid account.
+    pub fn set_asm_group(&mut self, group_id: u64) {
+        self.smtpapi.set_asm_group_id(group_id)
+    }
+
+    /// Set the unsubscribe groups shown to a recipient when they manage their subscriptions.
+    /// These groups must also already exist in the SendGrid account.
+    pub fn set_asm_groups_to_display(&mut self, group_ids: Vec<u64>) {
+        self.smtpapi.set_asm_groups_to_display(group_ids)
+    }
+
+    /// Apply a legacy template to the message through the X-SMTPAPI filters. The template must
+    /// already exist in the SendGrid account.
+    pub fn set_template(&mut self, template_id: &str) {
+        self.smtpapi.set_template(template_id)
+    }
+
+    /// Used internally for string encoding. Not needed for message building.
+    pub fn make_smtpapi_string(&self) -> SendgridResult<String> {
+        if !self.x_smtpapi.is_empty() || self.smtpapi.is_empty() {
+            return Ok(self.x_smtpapi.clone());
+        }
+
+        self.smtpapi.check_substitutions(self.to.len())?;
+        self.smtpapi.to_json()
+    }
+}
+
+#[test]
+fn png_attachment_round_trip() {
+    use std::io::Write;
+
+    // A 1x1 PNG, which isn't valid UTF-8 and so can't be read as a string.
+    let png: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x64,
+        0x60, 0xf8, 0x5f, 0x0f, 0x00, 0x02, 0x87, 0x01, 0x80, 0xeb, 0x47, 0xba, 0x92, 0x00, 0x00,
+        0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+    let path = std::env::temp_dir().join("sendgrid-rs-pixel.png");
+    File::create(&path).unwrap().write_all(png).unwrap();
+
+    let mut m = Mail::new();
+    m.add_attachment(&path).unwrap();
+
+    let attachment = &m.attachments["sendgrid-rs-pixel.png"];
+    assert_eq!(
+        attachment.content,
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="
+    );
+    assert_eq!(BASE64.decode(attachment.content.as_bytes()).unwrap(), png);
+}
+
+#[test]
+fn substitution_needs_a_value_per_recipient() {
+    let mut m = Mail::new();
+    m.add_to("alice@example.com");
+    m.add_to("bob@example.com");
+    m.add_substitution("-name-", vec!["Alice"]);
+
+    match m.make_smtpapi_string() {
+        Err(crate::errors::SendgridError(SendgridErrorKind::SubstitutionMismatch(_, 1, 2), _)) => {}
+        other => panic!("expected a substitution mismatch, got {:?}", other),
+    }
+
+    m.add_substitution("-name-", vec!["Alice", "Bob"]);
+    assert_eq!(
+        m.make_smtpapi_string().unwrap(),
+        r#"{"sub":{"-name-":["Alice","Bob"]}}"#
+    );
+}
+
+#[test]
+fn validate_addresses() {
+    for address in &["a@example.com", "first.last+tag@mail.example.co.uk"] {
+        assert!(is_valid_address(address), "{}", address);
+    }
+    for address in &[
+        "",
+        "example.com",
+        "a@",
+        "@example.com",
+        "a b@example.com",
+        "a@example",
+        "a@-x.com",
+    ] {
+        assert!(!is_valid_address(address), "{}", address);
+    }
+
+    let mut m = Mail::new();
+    m.add_from("me@example.com");
+    match m.validate() {
+        Err(crate::errors::SendgridError(SendgridErrorKind::NoRecipients, _)) => {}
+        other => panic!("expected no recipients, got {:?}", other),
+    }
+
+    m.add_to("you@example.com");
+    m.add_cc("them.example.com");
+    match m.validate() {
+        Err(crate::errors::SendgridError(SendgridErrorKind::InvalidEmail(ref address), _)) => {
+            assert_eq!(address, "them.example.com")
+        }
+        other => panic!("expected an invalid address, got {:?}", other),
+    }
+}
+
+#[test]
+fn owned_strings() {
+    // A message built from strings that don't outlive the function can still be returned.
+    fn welcome(address: String, name: String) -> Mail {
+        let subject = format!("Welcome, {}", name);
+        let mut m = Mail::text((address.clone(), name), subject, "me@example.com", "Hi");
+        m.add_header(String::from("X-User"), address);
+        m
+    }
+
+    let m = welcome(String::from("a@example.com"), String::from("A"));
+    assert_eq!(m.to, vec![Destination::with_name("a@example.com", "A")]);
+    assert_eq!(m.subject, "Welcome, A");
+    assert_eq!(m.headers["X-User"], "a@example.com");
+}
+
+#[test]
+fn dedupe_recipients() {
+    let mut m = Mail::new();
+    m.add_recipients(vec!["a@example.com", "b@example.com", "a@example.com"]);
+    m.add_cc_recipients(vec!["A@EXAMPLE.COM", "c@example.com", "a@Example.com"]);
+    m.add_bcc(("c@example.com", "C"));
+    m.add_bcc(("d@example.com", "D"));
+    m.dedupe_recipients();
+
+    assert_eq!(
+        m.to,
+        vec![
+            Destination::new("a@example.com"),
+            Destination::new("b@example.com")
+        ]
+    );
+    assert_eq!(
+        m.cc,
+        vec![
+            Destination::new("A@EXAMPLE.COM"),
+            Destination::new("c@example.com")
+        ]
+    );
+    assert_eq!(m.bcc, vec![Destination::with_name("d@example.com", "D")]);
+}
+
+#[test]
+fn with_recipient() {
+    let mut base = Mail::text("a@example.com", "Hello", "me@example.com", "Hi");
+    base.add_header("X-Campaign", "spring");
+    let m = base.with_recipient(("b@example.com", "B"));
+    assert_eq!(m.to, vec![Destination::with_name("b@example.com", "B")]);
+    assert_eq!(m.subject, "Hello");
+    assert_eq!(m.headers["X-Campaign"], "spring");
+    assert_eq!(base.to, vec![Destination::new("a@example.com")]);
+}
+
+#[test]
+fn recipients() {
+    let mut m = Mail::new();
+    m.add_bcc("c@example.com");
+    m.add_to(("a@example.com", "A"));
+    m.add_cc("b@example.com");
+
+    let got: Vec<_> = m.recipients().cloned().collect();
+    assert_eq!(
+        got,
+        vec![
+            Destination::with_name("a@example.com", "A"),
+            Destination::new("b@example.com"),
+            Destination::new("c@example.com"),
+        ]
+    );
+}
+
+#[test]
+fn priority_headers() {
+    let mut m = Mail::new();
+    m.set_priority(Priority::High);
+    assert_eq!(m.headers["X-Priority"], "1 (Highest)");
+    assert_eq!(m.headers["X-MSMail-Priority"], "High");
+    assert_eq!(m.headers["Importance"], "high");
+
+    m.set_priority(Priority::Low);
+    assert_eq!(m.headers["X-Priority"], "5 (Lowest)");
+    assert_eq!(m.headers["Importance"], "low");
+
+    m.set_priority(Priority::Normal);
+    assert!(m.headers.is_empty());
+}
+
+#[test]
+fn list_unsubscribe() {
+    let mut m = Mail::new();
+    m.set_list_unsubscribe("https://example.com/unsubscribe?u=42")
+        .unwrap();
+    assert_eq!(
+        m.headers["List-Unsubscribe"],
+        "<https://example.com/unsubscribe?u=42>"
+    );
+    assert_eq!(
+        m.headers["List-Unsubscribe-Post"],
+        "List-Unsubscribe=One-Click"
+    );
+
+    m.set_list_unsubscribe("mailto:unsubscribe@example.com")
+        .unwrap();
+    assert_eq!(
+        m.headers["List-Unsubscribe"],
+        "<mailto:unsubscribe@example.com>"
+    );
+    assert!(!m.headers.contains_key("List-Unsubscribe-Post"));
+
+    for target in &[
+        "http://example.com/unsubscribe",
+        "mailto:nobody",
+        "example.com",
+    ] {
+        let err = m.set_list_unsubscribe(target).unwrap_err();
+        assert!(err.is_kind(&SendgridErrorKind::InvalidUnsubscribe(target.to_string())));
     }
 }
+
+#[test]
+fn attachment_reader_chunks() {
+    // Short reads and data spanning several chunks must encode the same as all at once.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(1000).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let data: Vec<u8> = (0..100_001).map(|i| (i % 251) as u8).collect();
+    let mut m = Mail::new();
+    m.add_attachment_reader("data.bin", "application/octet-stream", Trickle(&data))
+        .unwrap();
+    assert_eq!(m.attachments["data.bin"].content, BASE64.encode(&data));
+}
+
+#[test]
+fn reserved_headers() {
+    let mut m = Mail::new();
+    m.try_add_header("X-Campaign", "spring").unwrap();
+    match m.try_add_header("subject", "Hijacked") {
+        Err(crate::errors::SendgridError(SendgridErrorKind::ReservedHeader(ref header), _)) => {
+            assert_eq!(header, "subject")
+        }
+        other => panic!("expected a reserved header error, got {:?}", other),
+    }
+    assert_eq!(m.headers.len(), 1);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_date() {
+    use chrono::TimeZone;
+
+    let mut m = Mail::new();
+    m.add_date_chrono(Utc.with_ymd_and_hms(2018, 3, 4, 5, 6, 7).unwrap());
+    assert_eq!(m.date, "Sun, 4 Mar 2018 05:06:07 +0000");
+}
+
+#[test]
+fn destination_display() {
+    assert_eq!(
+        Destination::new("a@example.com").to_string(),
+        "a@example.com"
+    );
+    assert_eq!(
+        Destination::with_name("a@example.com", "Support Team").to_string(),
+        "Support Team <a@example.com>"
+    );
+    assert_eq!(
+        Destination::with_name("a@example.com", "Doe, \"Jane\"").to_string(),
+        "\"Doe, \\\"Jane\\\"\" <a@example.com>"
+    );
+}
+
+#[test]
+fn destination_parse() {
+    assert_eq!(
+        Destination::parse("Alice <alice@example.com>").unwrap(),
+        Destination::with_name("alice@example.com", "Alice")
+    );
+    assert_eq!(
+        Destination::parse("\"Doe, \\\"Jane\\\"\" <jane@example.com>").unwrap(),
+        Destination::with_name("jane@example.com", "Doe, \"Jane\"")
+    );
+    assert_eq!(
+        Destination::parse(" <bob@example.com> ").unwrap(),
+        Destination::new("bob@example.com")
+    );
+    assert_eq!(
+        Destination::parse("bob@example.com").unwrap(),
+        Destination::new("bob@example.com")
+    );
+
+    for mailbox in &["Alice", "Alice <alice>", "\"Alice <alice@example.com>", ""] {
+        match Destination::parse(mailbox) {
+            Err(crate::errors::SendgridError(SendgridErrorKind::InvalidEmail(ref m), _)) => {
+                assert_eq!(m, mailbox)
+            }
+            other => panic!("expected an invalid address, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn destination_from_str() {
+    assert_eq!(
+        Destination::from("Alice <alice@example.com>"),
+        Destination::with_name("alice@example.com", "Alice")
+    );
+    assert_eq!(
+        Destination::from(String::from("bob@example.com")),
+        Destination::new("bob@example.com")
+    );
+    assert_eq!(
+        Destination::from("not an address"),
+        Destination::new("not an address")
+    );
+
+    let mut m = Mail::new();
+    m.add_to("Alice <alice@example.com>");
+    assert_eq!(m.to[0].address, "alice@example.com");
+}
+
+#[test]
+fn attachment_base64() {
+    let data = [0xfb, 0xff, 0x3e, 0x01];
+    let mut m = Mail::new();
+    m.add_attachment_base64("std.bin", "application/octet-stream", "+/8+AQ==")
+        .unwrap();
+    m.add_attachment_base64("url.bin", "application/octet-stream", "-_8-AQ")
+        .unwrap();
+    m.add_attachment_base64(
+        "wrapped.bin",
+        "application/octet-stream",
+        "+/8+\r\nAQ==\r\n",
+    )
+    .unwrap();
+    for name in &["std.bin", "url.bin", "wrapped.bin"] {
+        assert_eq!(m.attachments[*name].content, BASE64.encode(&data));
+    }
+
+    let err = m
+        .add_attachment_base64("bad.bin", "application/octet-stream", "not base64!")
+        .unwrap_err();
+    assert!(err.is_kind(&SendgridErrorKind::InvalidBase64(String::from("bad.bin"))));
+    assert!(!m.attachments.contains_key("bad.bin"));
+}
+
+#[test]
+fn recipients_csv() {
+    let mut m = Mail::new();
+    m.add_to_csv(" a@example.com, Bob <b@example.com>,")
+        .unwrap();
+    m.add_cc_csv(r#""Smith, Carol" <c@example.com>"#).unwrap();
+    assert_eq!(
+        m.to,
+        vec![
+            Destination::new("a@example.com"),
+            Destination::with_name("b@example.com", "Bob"),
+        ]
+    );
+    assert_eq!(
+        m.cc,
+        vec![Destination::with_name("c@example.com", "Smith, Carol")]
+    );
+
+    let err = m.add_bcc_csv("d@example.com, not an address").unwrap_err();
+    assert!(err.is_kind(&SendgridErrorKind::InvalidEmail(String::from(
+        "not an address"
+    ))));
+    assert!(m.bcc.is_empty());
+}
@@ -0,0 +1,227 @@
+//! Access to the mail settings of the account, which apply to every message it sends. These are
+//! the defaults that per-message settings such as `SGMailV3::set_footer` override.
+
+use crate::errors::SendgridResult;
+use crate::sg_client::{parse_api_response, SGClient};
+
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+static MAIL_SETTINGS_PATH: &str = "v3/mail_settings";
+
+/// The footer added to the bottom of every message.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct FooterSetting {
+    /// Whether the footer is added.
+    pub enabled: bool,
+    /// The footer added to the HTML content.
+    #[serde(default)]
+    pub html_content: String,
+    /// The footer added to the plain text content.
+    #[serde(default)]
+    pub plain_content: String,
+}
+
+/// Forwarding of spam reports to an address of your own.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ForwardSpamSetting {
+    /// Whether spam reports are forwarded.
+    pub enabled: bool,
+    /// The addresses spam reports are forwarded to, separated by commas.
+    #[serde(default)]
+    pub email: String,
+}
+
+/// Automatic removal of addresses from the bounce list after some time.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BouncePurgeSetting {
+    /// Whether bounces are purged.
+    pub enabled: bool,
+    /// How many days soft bounces are kept for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_bounces: Option<u32>,
+    /// How many days hard bounces are kept for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hard_bounces: Option<u32>,
+}
+
+/// The mail settings of the account. Fetching them fills in every setting, while only the
+/// settings that are `Some` are changed by an update.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountMailSettings {
+    pub footer: Option<FooterSetting>,
+    pub forward_spam: Option<ForwardSpamSetting>,
+    pub bounce_purge: Option<BouncePurgeSetting>,
+}
+
+// The path of a single mail setting.
+fn setting_path(name: &str) -> String {
+    format!("{}/{}", MAIL_SETTINGS_PATH, name)
+}
+
+impl SGClient {
+    /// Fetches the footer, spam forwarding and bounce purge settings of the account.
+    #[cfg(feature = "blocking")]
+    pub fn get_mail_settings(&self) -> SendgridResult<AccountMailSettings> {
+        Ok(AccountMailSettings {
+            footer: Some(self.mail_setting(Method::GET, "footer", None)?),
+            forward_spam: Some(self.mail_setting(Method::GET, "forward_spam", None)?),
+            bounce_purge: Some(self.mail_setting(Method::GET, "bounce_purge", None)?),
+        })
+    }
+
+    /// Changes the settings that are `Some`, leaving the rest alone, and returns the settings
+    /// as SendGrid stored them. Each setting is a separate request, so an error can leave the
+    /// settings before it changed.
+    #[cfg(feature = "blocking")]
+    pub fn update_mail_settings(
+        &self,
+        settings: &AccountMailSettings,
+    ) -> SendgridResult<AccountMailSettings> {
+        let mut updated = AccountMailSettings::default();
+        if let Some(ref footer) = settings.footer {
+            updated.footer = Some(self.mail_setting(Method::PATCH, "footer", Some(footer))?);
+        }
+        if let Some(ref forward_spam) = settings.forward_spam {
+            let body = Some(forward_spam);
+            updated.forward_spam = Some(self.mail_setting(Method::PATCH, "forward_spam", body)?);
+        }
+        if let Some(ref bounce_purge) = settings.bounce_purge {
+            let body = Some(bounce_purge);
+            updated.bounce_purge = Some(self.mail_setting(Method::PATCH, "bounce_purge", body)?);
+        }
+        Ok(updated)
+    }
+
+    // Fetch or change a single mail setting.
+    #[cfg(feature = "blocking")]
+    fn mail_setting<T: DeserializeOwned + Serialize>(
+        &self,
+        method: Method,
+        name: &str,
+        body: Option<&T>,
+    ) -> SendgridResult<T> {
+        let body = body.map(serde_json::to_string).transpose()?;
+        let (status, body) = self.api_request(method, &setting_path(name), body)?;
+        parse_api_response(status, body)
+    }
+
+    /// The asynchronous version of `get_mail_settings`.
+    pub async fn get_mail_settings_async(&self) -> SendgridResult<AccountMailSettings> {
+        let none: Option<&FooterSetting> = None;
+        let footer = self.mail_setting_async(Method::GET, "footer", none).await?;
+        let none: Option<&ForwardSpamSetting> = None;
+        let forward_spam = self
+            .mail_setting_async(Method::GET, "forward_spam", none)
+            .await?;
+        let none: Option<&BouncePurgeSetting> = None;
+        let bounce_purge = self
+            .mail_setting_async(Method::GET, "bounce_purge", none)
+            .await?;
+        Ok(AccountMailSettings {
+            footer: Some(footer),
+            forward_spam: Some(forward_spam),
+            bounce_purge: Some(bounce_purge),
+        })
+    }
+
+    /// The asynchronous version of `update_mail_settings`.
+    pub async fn update_mail_settings_async(
+        &self,
+        settings: &AccountMailSettings,
+    ) -> SendgridResult<AccountMailSettings> {
+        let mut updated = AccountMailSettings::default();
+        if let Some(ref footer) = settings.footer {
+            let body = Some(footer);
+            updated.footer = Some(
+                self.mail_setting_async(Method::PATCH, "footer", body)
+                    .await?,
+            );
+        }
+        if let Some(ref forward_spam) = settings.forward_spam {
+            let body = Some(forward_spam);
+            let setting = self
+                .mail_setting_async(Method::PATCH, "forward_spam", body)
+                .await?;
+            updated.forward_spam = Some(setting);
+        }
+        if let Some(ref bounce_purge) = settings.bounce_purge {
+            let body = Some(bounce_purge);
+            let setting = self
+                .mail_setting_async(Method::PATCH, "bounce_purge", body)
+                .await?;
+            updated.bounce_purge = Some(setting);
+        }
+        Ok(updated)
+    }
+
+    // The asynchronous version of `mail_setting`.
+    async fn mail_setting_async<T: DeserializeOwned + Serialize>(
+        &self,
+        method: Method,
+        name: &str,
+        body: Option<&T>,
+    ) -> SendgridResult<T> {
+        let body = body.map(serde_json::to_string).transpose()?;
+        let (status, body) = self
+            .api_request_async(method, &setting_path(name), body)
+            .await?;
+        parse_api_response(status, body)
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn mail_settings_round_trip() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(200, r#"{"enabled": true, "html_content": "<p>Bye</p>"}"#);
+    mock.push_response(200, r#"{"enabled": false, "email": ""}"#);
+    mock.push_response(
+        200,
+        r#"{"enabled": true, "soft_bounces": 5, "hard_bounces": 10}"#,
+    );
+    mock.push_response(200, r#"{"enabled": true, "email": "spam@example.com"}"#);
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .build();
+
+    let settings = sg.get_mail_settings().unwrap();
+    assert_eq!(
+        settings.footer,
+        Some(FooterSetting {
+            enabled: true,
+            html_content: String::from("<p>Bye</p>"),
+            plain_content: String::new(),
+        })
+    );
+    assert_eq!(settings.bounce_purge.unwrap().hard_bounces, Some(10));
+
+    let update = AccountMailSettings {
+        forward_spam: Some(ForwardSpamSetting {
+            enabled: true,
+            email: String::from("spam@example.com"),
+        }),
+        ..AccountMailSettings::default()
+    };
+    let updated = sg.update_mail_settings(&update).unwrap();
+    assert_eq!(updated, update);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(
+        requests[0].url,
+        "https://api.sendgrid.com/v3/mail_settings/footer"
+    );
+    assert_eq!(requests[3].method, Method::PATCH);
+    assert_eq!(
+        requests[3].url,
+        "https://api.sendgrid.com/v3/mail_settings/forward_spam"
+    );
+    assert_eq!(
+        requests[3].body.as_deref(),
+        Some(r#"{"enabled":true,"email":"spam@example.com"}"#)
+    );
+}
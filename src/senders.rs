@@ -0,0 +1,75 @@
+//! Access to the sender identities verified for the account. SendGrid rejects messages whose
+//! from address isn't a verified sender or on an authenticated domain with a 403.
+
+use crate::errors::SendgridResult;
+use crate::sg_client::{parse_api_response, SGClient};
+
+use reqwest::Method;
+
+static VERIFIED_SENDERS_PATH: &str = "v3/verified_senders";
+
+/// A single sender identity.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct VerifiedSender {
+    /// SendGrid's id for the sender.
+    pub id: u64,
+    /// The name the sender is listed under in SendGrid.
+    pub nickname: String,
+    /// The address messages are sent from.
+    pub from_email: String,
+    /// The display name messages are sent with.
+    #[serde(default)]
+    pub from_name: String,
+    /// Whether the owner of the address has confirmed it. Messages from an unverified sender are
+    /// rejected.
+    pub verified: bool,
+}
+
+// The senders are wrapped in a results array.
+#[derive(Deserialize)]
+struct VerifiedSendersBody {
+    results: Vec<VerifiedSender>,
+}
+
+// Decode a list of senders, dropping the wrapper.
+fn parse_senders(status: u16, raw_body: String) -> SendgridResult<Vec<VerifiedSender>> {
+    parse_api_response::<VerifiedSendersBody>(status, raw_body).map(|body| body.results)
+}
+
+impl SGClient {
+    /// Lists the sender identities of the account, including those still awaiting verification.
+    /// This can be used to check a from address before sending instead of waiting for SendGrid
+    /// to reject it. Addresses on an authenticated domain can be sent from without being listed.
+    #[cfg(feature = "blocking")]
+    pub fn list_verified_senders(&self) -> SendgridResult<Vec<VerifiedSender>> {
+        let (status, body) = self.api_request(Method::GET, VERIFIED_SENDERS_PATH, None)?;
+        parse_senders(status, body)
+    }
+
+    /// The asynchronous version of `list_verified_senders`.
+    pub async fn list_verified_senders_async(&self) -> SendgridResult<Vec<VerifiedSender>> {
+        let (status, body) = self
+            .api_request_async(Method::GET, VERIFIED_SENDERS_PATH, None)
+            .await?;
+        parse_senders(status, body)
+    }
+}
+
+#[test]
+fn parse_senders_response() {
+    let body = String::from(
+        r#"{"results": [
+            {"id": 1, "nickname": "Support", "from_email": "support@example.com",
+             "from_name": "Example Support", "reply_to": "support@example.com",
+             "verified": true, "locked": false},
+            {"id": 2, "nickname": "New", "from_email": "new@example.com", "verified": false}
+        ]}"#,
+    );
+    let got = parse_senders(200, body).unwrap();
+    assert_eq!(got.len(), 2);
+    assert_eq!(got[0].from_email, "support@example.com");
+    assert!(got[0].verified);
+    assert_eq!(got[1].nickname, "New");
+    assert_eq!(got[1].from_name, "");
+    assert!(!got[1].verified);
+}
@@ -1,20 +1,505 @@
-use errors::SendgridResult;
+use crate::errors::{ApiError, SendgridError, SendgridErrorKind, SendgridResult};
 
-use mail::Mail;
+use crate::mail::{Destination, Disposition, Mail};
+use crate::transport::{PreparedRequest, RawResponse, ReqwestTransport, Transport};
+use crate::v3::SGMailV3;
 
-use std::io::Read;
+use std::sync::Arc;
+#[cfg(feature = "blocking")]
+use std::sync::{Mutex, OnceLock};
+#[cfg(feature = "blocking")]
+use std::thread;
+use std::time::Duration;
 
-use reqwest::header::{Authorization, Bearer, ContentType, Headers, UserAgent};
-use reqwest::Client;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, USER_AGENT,
+};
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 
 use url::form_urlencoded::Serializer;
 
-static API_URL: &'static str = "https://api.sendgrid.com/api/mail.send.json?";
+pub(crate) static API_URL: &str = "https://api.sendgrid.com";
+static MAIL_SEND_PATH: &str = "api/mail.send.json";
+pub(crate) static V3_MAIL_SEND_PATH: &str = "v3/mail/send";
+static BATCH_PATH: &str = "v3/mail/batch";
+static SCHEDULED_SENDS_PATH: &str = "v3/user/scheduled_sends";
+static DEFAULT_USER_AGENT: &str = "sendgrid-rs";
+// The charset is stated so that non-ASCII subjects and bodies, which are percent encoded as
+// UTF-8, are not decoded as Latin-1.
+static FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded; charset=utf-8";
+static JSON_CONTENT_TYPE: &str = "application/json";
+static IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+static ON_BEHALF_OF_HEADER: &str = "On-Behalf-Of";
+
+/// The environment variable `SGClient::from_env` reads the API key from, which is the name
+/// SendGrid uses in its documentation.
+pub static API_KEY_VAR: &str = "SENDGRID_API_KEY";
+
+/// The timeout applied to requests unless one is set with `SGClient::with_timeout`.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest request body SendGrid accepts, including the encoding of any attachments, unless
+/// another limit is set with `SGClientBuilder::max_payload_size`.
+pub const MAX_PAYLOAD_SIZE: usize = 30 * 1024 * 1024;
+
+/// A message that can be sent with `SGClient::send`. The message decides which API it is sent to
+/// and how it is encoded: a V2 `Mail` is form encoded, and a V3 `SGMailV3` is sent as JSON.
+pub trait Message {
+    /// The path of the endpoint that sends this kind of message.
+    fn path(&self) -> &'static str;
+
+    /// The content type of the encoded message.
+    fn content_type(&self) -> &'static str;
+
+    /// Encode the message as the body of the request.
+    fn into_body(self) -> SendgridResult<String>;
+
+    /// The filename and base64 encoded size of each attachment, which are checked against the
+    /// attachment limits of the client before sending.
+    fn attachment_sizes(&self) -> Vec<(&str, usize)> {
+        Vec::new()
+    }
+
+    /// The key sent in the `Idempotency-Key` header, if the message has one.
+    fn idempotency_key(&self) -> Option<&str> {
+        None
+    }
+
+    /// Extra headers to send with the request, as name and value pairs.
+    fn request_headers(&self) -> Vec<(&str, &str)> {
+        Vec::new()
+    }
+}
+
+impl Message for Mail {
+    fn path(&self) -> &'static str {
+        MAIL_SEND_PATH
+    }
+
+    fn content_type(&self) -> &'static str {
+        FORM_CONTENT_TYPE
+    }
+
+    fn into_body(self) -> SendgridResult<String> {
+        make_post_body(self)
+    }
+
+    fn attachment_sizes(&self) -> Vec<(&str, usize)> {
+        let attachments = self.attachments.iter();
+        attachments
+            .map(|(name, a)| (name.as_str(), a.content.len()))
+            .collect()
+    }
+
+    fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    fn request_headers(&self) -> Vec<(&str, &str)> {
+        let headers = self.request_headers.iter();
+        headers
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+impl Message for &SGMailV3 {
+    fn path(&self) -> &'static str {
+        V3_MAIL_SEND_PATH
+    }
+
+    fn content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn into_body(self) -> SendgridResult<String> {
+        self.validate()?;
+        Ok(serde_json::to_string(self)?)
+    }
+
+    fn attachment_sizes(&self) -> Vec<(&str, usize)> {
+        SGMailV3::attachment_sizes(self)
+    }
+
+    fn idempotency_key(&self) -> Option<&str> {
+        SGMailV3::idempotency_key(self)
+    }
+
+    fn request_headers(&self) -> Vec<(&str, &str)> {
+        SGMailV3::request_headers(self)
+    }
+}
+
+// An owned V3 message is sent exactly like a borrowed one.
+impl Message for SGMailV3 {
+    fn path(&self) -> &'static str {
+        <&SGMailV3>::path(&self)
+    }
+
+    fn content_type(&self) -> &'static str {
+        <&SGMailV3>::content_type(&self)
+    }
+
+    fn into_body(self) -> SendgridResult<String> {
+        <&SGMailV3>::into_body(&self)
+    }
+
+    fn attachment_sizes(&self) -> Vec<(&str, usize)> {
+        SGMailV3::attachment_sizes(self)
+    }
+
+    fn idempotency_key(&self) -> Option<&str> {
+        SGMailV3::idempotency_key(self)
+    }
+
+    fn request_headers(&self) -> Vec<(&str, &str)> {
+        SGMailV3::request_headers(self)
+    }
+}
+
+/// The parsed result of a successful call to the SendGrid API.
+#[derive(Debug)]
+pub struct SendResponse {
+    /// The HTTP status code returned by SendGrid.
+    pub status: u16,
+    /// The value of the `X-Message-Id` header. This can be used to correlate the message with
+    /// data sent to the event webhook. Responses are only returned for accepted messages, so
+    /// this is set whenever SendGrid includes the header.
+    pub message_id: Option<String>,
+    /// The `message` field of the JSON body, which is "success" for accepted messages.
+    pub message: Option<String>,
+    /// Problems SendGrid reported that didn't stop the message from being accepted, such as a
+    /// substitution tag that matched nothing. This is empty unless the body had `warnings`.
+    pub warnings: Vec<String>,
+    /// The body of the response, parsed. It is the default for a body that isn't JSON, such as
+    /// the empty body of a V3 send.
+    pub body: SuccessBody,
+    /// The body of the response exactly as SendGrid returned it.
+    pub raw_body: String,
+    /// How many requests were made to deliver the message, including any retries.
+    pub attempts: u32,
+    /// Whether SendGrid answered any of the attempts with a 429, even though a retry then
+    /// succeeded. A send that is often rate limited is a sign to slow down.
+    pub rate_limited: bool,
+    /// The rate limit SendGrid reported for the endpoint, if it sent the headers for one.
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl SendResponse {
+    /// Whether SendGrid reported the message as sent with a `message` of "success". Sends to the
+    /// V3 API are accepted with an empty body, so this is false for them and `status` should be
+    /// checked instead.
+    pub fn is_success(&self) -> bool {
+        self.body.is_success()
+    }
+
+    /// How many times the request was retried before SendGrid accepted it, which is zero when
+    /// the first attempt succeeded.
+    pub fn retry_count(&self) -> u32 {
+        self.attempts.saturating_sub(1)
+    }
+}
+
+/// The rate limit state of an endpoint, read from the `X-RateLimit-*` headers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    /// The number of requests allowed in the current window.
+    pub limit: u64,
+    /// The number of requests left in the current window.
+    pub remaining: u64,
+    /// When the current window resets, as a Unix timestamp in seconds.
+    pub reset: u64,
+}
+
+/// Controls how a send is retried when SendGrid responds with a 429 or a 5xx status. Other
+/// errors are never retried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The most retries to make after the initial request.
+    pub max_retries: u32,
+    /// The delay before the first retry. Each subsequent retry waits twice as long as the last,
+    /// unless SendGrid specifies a delay with the `Retry-After` header.
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    // How long to wait before the given retry, preferring the delay SendGrid asked for.
+    fn delay(&self, retry: u32, headers: &HeaderMap) -> Duration {
+        retry_after(headers).unwrap_or_else(|| {
+            self.base_delay
+                .checked_mul(2u32.saturating_pow(retry - 1))
+                .unwrap_or(Duration::MAX)
+        })
+    }
+}
+
+/// What happens to the scheduled sends of a batch.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduledSendStatus {
+    /// The messages will not be sent.
+    Cancel,
+    /// The messages are held until the pause is removed.
+    Pause,
+}
+
+/// A batch of scheduled sends that SendGrid has paused or cancelled.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduledSend {
+    /// The ID of the batch.
+    pub batch_id: String,
+    /// What SendGrid will do with the batch.
+    pub status: ScheduledSendStatus,
+}
+
+// The JSON body SendGrid returns when it creates a batch ID.
+#[derive(Deserialize)]
+struct BatchId {
+    batch_id: String,
+}
+
+/// The JSON body SendGrid returns when it accepts a message. The V2 API returns
+/// `{"message":"success"}`, while the V3 API returns an empty body, which parses as the default.
+/// Fields SendGrid adds later are ignored.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SuccessBody {
+    /// The outcome SendGrid reported, which is "success" for accepted messages.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Problems that didn't stop the message from being accepted.
+    #[serde(default)]
+    pub warnings: Vec<ApiError>,
+}
+
+impl SuccessBody {
+    /// Whether the `message` is "success".
+    pub fn is_success(&self) -> bool {
+        self.message.as_deref() == Some("success")
+    }
+}
+
+// The JSON body SendGrid returns when it rejects a request.
+#[derive(Deserialize)]
+struct ResponseBody {
+    #[serde(default)]
+    errors: Vec<ApiError>,
+}
 
 /// This is the struct that allows you to authenticate to the SendGrid API.
-/// It's only field is the API key which allows you to send messages.
+/// It holds the API key which allows you to send messages, along with the base URL of the API
+/// to send them to. The underlying HTTP client is kept for the lifetime of the struct so
+/// connections can be reused between sends. Cloning the client is cheap, and the clones share
+/// the underlying HTTP client.
+#[derive(Clone)]
 pub struct SGClient {
     api_key: String,
+    base_url: String,
+    transport: Arc<dyn Transport>,
+    timeout: Duration,
+    retry: Option<RetryPolicy>,
+    user_agent: String,
+    max_payload_size: usize,
+    max_attachments: usize,
+    max_attachment_size: usize,
+    on_behalf_of: Option<String>,
+}
+
+/// Configures and builds an `SGClient`. Every option defaults to the behaviour of
+/// `SGClient::new`, so only the options that matter need to be set.
+pub struct SGClientBuilder {
+    api_key: String,
+    base_url: String,
+    client: Option<Client>,
+    #[cfg(feature = "blocking")]
+    blocking_client: Option<reqwest::blocking::Client>,
+    timeout: Duration,
+    retry: Option<RetryPolicy>,
+    user_agent: String,
+    proxy: Option<reqwest::Proxy>,
+    gzip: bool,
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout: Option<Duration>,
+    transport: Option<Arc<dyn Transport>>,
+    max_payload_size: usize,
+    max_attachments: usize,
+    max_attachment_size: usize,
+}
+
+impl SGClientBuilder {
+    /// Sends requests to the given base URL instead of the default US endpoint.
+    pub fn base_url<T: Into<String>>(mut self, base_url: T) -> SGClientBuilder {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets how long a request may take before it is abandoned, which defaults to 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> SGClientBuilder {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries sends that SendGrid rejects with a 429 or a 5xx status according to the given
+    /// policy. By default nothing is retried.
+    pub fn retries(mut self, policy: RetryPolicy) -> SGClientBuilder {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request, which defaults to `sendgrid-rs`.
+    /// SendGrid support can use this to identify the application, such as
+    /// `myapp/2.3 (sendgrid-rs)`.
+    pub fn user_agent(mut self, user_agent: &str) -> SGClientBuilder {
+        self.user_agent = String::from(user_agent);
+        self
+    }
+
+    /// Sends requests through the given proxy, which may include credentials with
+    /// `reqwest::Proxy::basic_auth`. The proxy is used by both the blocking and asynchronous
+    /// clients, but has no effect on clients passed to the builder pre-built.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> SGClientBuilder {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Turns compression of responses on or off. It is on by default, so large responses from
+    /// the list endpoints are sent gzipped and decompressed transparently. Turning it off can
+    /// help with proxies that mangle encodings. Like the proxy, this doesn't affect pre-built
+    /// clients.
+    pub fn gzip(mut self, enable: bool) -> SGClientBuilder {
+        self.gzip = enable;
+        self
+    }
+
+    /// Sets how many idle connections to SendGrid are kept open for reuse. Like reqwest, there
+    /// is no limit by default. Lowering it saves resources when sends come in bursts.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> SGClientBuilder {
+        self.pool_max_idle_per_host = max;
+        self
+    }
+
+    /// Sets how long an idle connection is kept open for reuse, or `None` to keep them open
+    /// indefinitely. The default of 90 seconds is the same as reqwest's. Connections idle for
+    /// longer than SendGrid or a proxy allows are reset, so this may need to be lowered.
+    pub fn pool_idle_timeout<D: Into<Option<Duration>>>(mut self, timeout: D) -> SGClientBuilder {
+        self.pool_idle_timeout = timeout.into();
+        self
+    }
+
+    /// Sets the largest request body to send, which defaults to `MAX_PAYLOAD_SIZE`. Larger
+    /// messages fail with `SendgridErrorKind::PayloadTooLarge` without being uploaded. This only
+    /// needs to change if SendGrid changes its limit.
+    pub fn max_payload_size(mut self, limit: usize) -> SGClientBuilder {
+        self.max_payload_size = limit;
+        self
+    }
+
+    /// Sets the most attachments a message may have. There is no limit by default, since
+    /// SendGrid only limits the total size of a message.
+    pub fn max_attachments(mut self, limit: usize) -> SGClientBuilder {
+        self.max_attachments = limit;
+        self
+    }
+
+    /// Sets the largest an attachment may be once base64 encoded, which is how it counts
+    /// towards SendGrid's limit on the size of the whole message. The default is that limit,
+    /// `MAX_PAYLOAD_SIZE`. Messages with larger attachments fail with
+    /// `SendgridErrorKind::AttachmentTooLarge` without being sent.
+    pub fn max_attachment_size(mut self, limit: usize) -> SGClientBuilder {
+        self.max_attachment_size = limit;
+        self
+    }
+
+    /// Sends every request through the given transport instead of making HTTP requests, such as
+    /// a `MockTransport` in tests. The HTTP options of the builder, like the proxy, are ignored.
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> SGClientBuilder {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Sends asynchronous requests with a pre-built reqwest client.
+    pub fn client(mut self, client: Client) -> SGClientBuilder {
+        self.client = Some(client);
+        self
+    }
+
+    /// Sends blocking requests with a pre-built reqwest client.
+    #[cfg(feature = "blocking")]
+    pub fn blocking_client(mut self, client: reqwest::blocking::Client) -> SGClientBuilder {
+        self.blocking_client = Some(client);
+        self
+    }
+
+    /// Builds the client. An asynchronous HTTP client is created here if one wasn't provided,
+    /// while a blocking one is only created when the first blocking request is sent.
+    pub fn build(self) -> SGClient {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => {
+                let proxy = self.proxy;
+                let gzip = self.gzip;
+                let max_idle = self.pool_max_idle_per_host;
+                let idle_timeout = self.pool_idle_timeout;
+                Arc::new(ReqwestTransport {
+                    client: self.client.unwrap_or_else(|| {
+                        let mut builder = Client::builder()
+                            .connect_timeout(DEFAULT_TIMEOUT)
+                            .gzip(gzip)
+                            .pool_max_idle_per_host(max_idle)
+                            .pool_idle_timeout(idle_timeout);
+                        if let Some(ref proxy) = proxy {
+                            builder = builder.proxy(proxy.clone());
+                        }
+                        builder.build().expect("could not build the HTTP client")
+                    }),
+                    #[cfg(feature = "blocking")]
+                    blocking_client: self.blocking_client.map(OnceLock::from).unwrap_or_default(),
+                    #[cfg(feature = "blocking")]
+                    build_blocking_client: Box::new(move || {
+                        let mut builder = reqwest::blocking::Client::builder()
+                            .connect_timeout(DEFAULT_TIMEOUT)
+                            .gzip(gzip)
+                            .pool_max_idle_per_host(max_idle)
+                            .pool_idle_timeout(idle_timeout);
+                        if let Some(ref proxy) = proxy {
+                            builder = builder.proxy(proxy.clone());
+                        }
+                        builder.build().expect("could not build the HTTP client")
+                    }),
+                })
+            }
+        };
+
+        SGClient {
+            api_key: self.api_key,
+            base_url: self.base_url,
+            transport,
+            timeout: self.timeout,
+            retry: self.retry,
+            user_agent: self.user_agent,
+            max_payload_size: self.max_payload_size,
+            max_attachments: self.max_attachments,
+            max_attachment_size: self.max_attachment_size,
+            on_behalf_of: None,
+        }
+    }
+}
+
+// Read an API key from an environment variable, treating an empty value as a missing one.
+fn api_key_from_env(var: &str) -> SendgridResult<String> {
+    let key = std::env::var(var).ok().filter(|key| !key.is_empty());
+    key.ok_or_else(|| SendgridErrorKind::MissingApiKey(var.to_string()).into())
+}
+
+// Join a base URL and a path with exactly one slash between them, regardless of whether the base
+// has a trailing slash.
+pub(crate) fn make_url(base_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
 }
 
 // Given a form value and a key, generate the correct key.
@@ -28,29 +513,56 @@ fn make_form_key(form: &str, key: &str) -> String {
     value
 }
 
-// Use the URL form encoder to properly generate the body used in the mail send request.
-fn make_post_body(mut mail_info: Mail) -> SendgridResult<String> {
-    let body = String::new();
-    let mut encoder = Serializer::new(body);
+// SendGrid expects recipients as parallel arrays of addresses and names, such as to[] and
+// toname[]. The names are only sent if at least one recipient has a non-empty one, in which case
+// every recipient gets an entry so the two arrays stay aligned, with an empty name for those
+// without one. An empty name is treated the same as no name.
+fn append_destinations(encoder: &mut Serializer<String>, field: &str, dests: &[Destination]) {
+    for dest in dests {
+        encoder.append_pair(&format!("{}[]", field), &dest.address);
+    }
 
-    for to in mail_info.to.iter() {
-        encoder.append_pair("to[]", &to);
+    if dests
+        .iter()
+        .any(|dest| dest.name.as_deref().is_some_and(|name| !name.is_empty()))
+    {
+        for dest in dests {
+            let name = dest.name.as_deref().unwrap_or("");
+            encoder.append_pair(&format!("{}name[]", field), name);
+        }
     }
+}
 
-    for to_name in mail_info.to_names.iter() {
-        encoder.append_pair("toname[]", &to_name);
+// Append a field that SendGrid treats as unset when it is missing, leaving it out when it is
+// empty to keep the body small.
+fn append_optional(encoder: &mut Serializer<String>, key: &str, value: &str) {
+    if !value.is_empty() {
+        encoder.append_pair(key, value);
     }
+}
 
-    for cc in mail_info.cc.iter() {
-        encoder.append_pair("cc[]", &cc);
+// Use the URL form encoder to properly generate the body used in the mail send request. A
+// message needs a text body, an HTML body, or both.
+fn make_post_body(mut mail_info: Mail) -> SendgridResult<String> {
+    if mail_info.text.is_empty() && mail_info.html.is_empty() {
+        return Err(SendgridErrorKind::MissingBody.into());
     }
 
-    for bcc in mail_info.bcc.iter() {
-        encoder.append_pair("bcc[]", &bcc);
+    let body = String::new();
+    let mut encoder = Serializer::new(body);
+
+    append_destinations(&mut encoder, "to", &mail_info.to);
+    append_destinations(&mut encoder, "cc", &mail_info.cc);
+    append_destinations(&mut encoder, "bcc", &mail_info.bcc);
+
+    for (filename, attachment) in &mail_info.attachments {
+        encoder.append_pair(&make_form_key("files", filename), &attachment.content);
     }
 
-    for (attachment, contents) in &mail_info.attachments {
-        encoder.append_pair(&make_form_key("files", attachment), contents);
+    for (filename, attachment) in &mail_info.attachments {
+        if let Disposition::Inline { ref content_id } = attachment.disposition {
+            encoder.append_pair(&make_form_key("content", filename), content_id);
+        }
     }
 
     for (id, value) in &mail_info.content {
@@ -59,44 +571,595 @@ fn make_post_body(mut mail_info: Mail) -> SendgridResult<String> {
 
     encoder.append_pair("from", &mail_info.from);
     encoder.append_pair("subject", &mail_info.subject);
-    encoder.append_pair("html", &mail_info.html);
-    encoder.append_pair("text", &mail_info.text);
-    encoder.append_pair("fromname", &mail_info.from_name);
-    encoder.append_pair("replyto", &mail_info.reply_to);
-    encoder.append_pair("date", &mail_info.date);
-    encoder.append_pair("headers", &mail_info.make_header_string()?);
-    encoder.append_pair("x-smtpapi", &mail_info.x_smtpapi);
+    append_optional(&mut encoder, "html", &mail_info.html);
+    append_optional(&mut encoder, "text", &mail_info.text);
+    append_optional(&mut encoder, "fromname", &mail_info.from_name);
+    let reply_to = mail_info.reply_to.as_ref().map(ToString::to_string);
+    append_optional(&mut encoder, "replyto", reply_to.as_deref().unwrap_or(""));
+    append_optional(&mut encoder, "date", &mail_info.date);
+    if !mail_info.headers.is_empty() {
+        encoder.append_pair("headers", &mail_info.make_header_string()?);
+    }
+    append_optional(&mut encoder, "x-smtpapi", &mail_info.make_smtpapi_string()?);
 
     Ok(encoder.finish())
 }
 
+// Rate limiting and server errors are worth retrying, while other errors will fail again.
+fn is_retryable(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+// Read a numeric header, treating a malformed value the same as a missing one.
+fn numeric_header(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+// Read the number of seconds SendGrid asked us to wait before retrying.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    numeric_header(headers, "Retry-After").map(Duration::from_secs)
+}
+
+// Read the rate limit headers, which are only useful when all three are present.
+fn rate_limit(headers: &HeaderMap) -> Option<RateLimit> {
+    Some(RateLimit {
+        limit: numeric_header(headers, "X-RateLimit-Limit")?,
+        remaining: numeric_header(headers, "X-RateLimit-Remaining")?,
+        reset: numeric_header(headers, "X-RateLimit-Reset")?,
+    })
+}
+
+// Read the message ID SendGrid assigned to an accepted message.
+fn message_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("X-Message-Id")
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+// Build the error for a response SendGrid did not accept, with any error details it included.
+fn api_error(status: u16, raw_body: String) -> SendgridError {
+    let errors = serde_json::from_str::<ResponseBody>(&raw_body)
+        .map(|b| b.errors)
+        .unwrap_or_default();
+    SendgridErrorKind::Api(status, errors, raw_body).into()
+}
+
+// Decode the JSON body of a successful call to the V3 API, or return the error SendGrid sent.
+pub(crate) fn parse_api_response<T: DeserializeOwned>(
+    status: u16,
+    raw_body: String,
+) -> SendgridResult<T> {
+    if !(200..300).contains(&status) {
+        return Err(api_error(status, raw_body));
+    }
+    Ok(serde_json::from_str(&raw_body)?)
+}
+
+// Check that a call to the V3 API succeeded, for endpoints that return no body.
+pub(crate) fn check_api_response(status: u16, raw_body: String) -> SendgridResult<()> {
+    if !(200..300).contains(&status) {
+        return Err(api_error(status, raw_body));
+    }
+    Ok(())
+}
+
+// The wording SendGrid uses when a batch has already gone out, so it has no scheduled sends left
+// to pause or cancel.
+static ALREADY_SENT_PHRASES: &[&str] = &["already been sent", "already sent"];
+
+// Decode the response to pausing or cancelling a batch. A batch that has already gone out is
+// reported as its own error kind caused by the API error, while other rejections, such as a
+// malformed batch ID, are left as API errors.
+fn parse_scheduled_send(
+    batch_id: &str,
+    status: u16,
+    raw_body: String,
+) -> SendgridResult<ScheduledSend> {
+    parse_api_response(status, raw_body).map_err(|err| {
+        let unscheduled = match *err.kind() {
+            SendgridErrorKind::Api(400, ref errors, _)
+            | SendgridErrorKind::Api(404, ref errors, _) => errors.iter().any(|e| {
+                let message = e.message.to_lowercase();
+                ALREADY_SENT_PHRASES
+                    .iter()
+                    .any(|phrase| message.contains(phrase))
+            }),
+            _ => false,
+        };
+        if unscheduled {
+            SendgridError::with_chain(
+                err,
+                SendgridErrorKind::BatchNotScheduled(String::from(batch_id)),
+            )
+        } else {
+            err
+        }
+    })
+}
+
+// Turn the pieces of an HTTP response into a SendResponse, or an error if SendGrid did not accept
+// the message. A retryable error after more than one attempt means the retries ran out, and a
+// rejected API key gets its own kind since it is the most common setup mistake.
+fn parse_response(
+    status: u16,
+    headers: &HeaderMap,
+    raw_body: String,
+    attempts: u32,
+) -> SendgridResult<SendResponse> {
+    if !(200..300).contains(&status) {
+        let err = api_error(status, raw_body);
+        let kind = match status {
+            401 => SendgridErrorKind::Unauthorized,
+            403 => SendgridErrorKind::Forbidden,
+            _ if attempts > 1 && is_retryable(status) => {
+                SendgridErrorKind::RetriesExhausted(attempts)
+            }
+            _ => return Err(err),
+        };
+        return Err(SendgridError::with_chain(err, kind));
+    }
+
+    let body: SuccessBody = serde_json::from_str(&raw_body).unwrap_or_default();
+    Ok(SendResponse {
+        status,
+        message_id: message_id(headers),
+        message: body.message.clone(),
+        warnings: body.warnings.iter().map(ToString::to_string).collect(),
+        body,
+        raw_body,
+        attempts,
+        rate_limited: false,
+        rate_limit: rate_limit(headers),
+    })
+}
+
 impl SGClient {
     /// Makes a new SendGrid cient with the specified API key.
     pub fn new(key: String) -> SGClient {
-        SGClient { api_key: key }
+        SGClient::builder(key).build()
+    }
+
+    /// Makes a new SendGrid client with the API key in the `SENDGRID_API_KEY` environment
+    /// variable. This returns `SendgridErrorKind::MissingApiKey` if it isn't set or is empty.
+    pub fn from_env() -> SendgridResult<SGClient> {
+        Ok(SGClient::new(api_key_from_env(API_KEY_VAR)?))
+    }
+
+    /// Starts building a SendGrid client with the specified API key, for when more than the
+    /// defaults need to be configured.
+    pub fn builder(key: String) -> SGClientBuilder {
+        SGClientBuilder {
+            api_key: key,
+            base_url: String::from(API_URL),
+            client: None,
+            #[cfg(feature = "blocking")]
+            blocking_client: None,
+            timeout: DEFAULT_TIMEOUT,
+            retry: None,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            proxy: None,
+            gzip: true,
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            transport: None,
+            max_payload_size: MAX_PAYLOAD_SIZE,
+            max_attachments: usize::MAX,
+            max_attachment_size: MAX_PAYLOAD_SIZE,
+        }
+    }
+
+    /// Makes a new SendGrid client that sends requests to the given base URL instead of the
+    /// default US endpoint. This can be used for the EU region, which is served from
+    /// `https://api.eu.sendgrid.com`, or to point the client at a mock server in tests.
+    pub fn with_base_url<T: Into<String>>(key: String, base_url: T) -> SGClient {
+        SGClient::builder(key).base_url(base_url).build()
+    }
+
+    /// Returns a copy of the client that makes every request on behalf of the given subuser,
+    /// with the `On-Behalf-Of` header. This lets one parent account API key send as each of its
+    /// subusers. The copy shares the HTTP client of this one, so making one per request is
+    /// cheap.
+    pub fn on_behalf_of(&self, subuser: &str) -> SGClient {
+        SGClient {
+            on_behalf_of: Some(subuser.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Sets how long a request may take before it is abandoned, which defaults to 30 seconds.
+    /// The timeout covers the whole request, from connecting to reading the response, and a
+    /// request that exceeds it fails with `SendgridErrorKind::Timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> SGClient {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries sends that SendGrid rejects with a 429 or a 5xx status according to the given
+    /// policy. By default nothing is retried. When the retries run out, the error returned has
+    /// the kind `SendgridErrorKind::RetriesExhausted` and is caused by the final API error.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> SGClient {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request, which defaults to `sendgrid-rs`.
+    pub fn with_user_agent(mut self, user_agent: &str) -> SGClient {
+        self.user_agent = String::from(user_agent);
+        self
+    }
+
+    // If a response should be retried, how long to wait before doing so.
+    fn retry_delay(&self, status: u16, headers: &HeaderMap, attempts: u32) -> Option<Duration> {
+        match self.retry {
+            Some(ref policy) if is_retryable(status) && attempts <= policy.max_retries => {
+                Some(policy.delay(attempts, headers))
+            }
+            _ => None,
+        }
+    }
+
+    /// Makes a new SendGrid client that sends asynchronous requests with a pre-built reqwest
+    /// client. This allows one connection pool to be shared with the rest of an application.
+    pub fn with_client(key: String, client: Client) -> SGClient {
+        SGClient::builder(key).client(client).build()
+    }
+
+    /// Makes a new SendGrid client that sends blocking requests with a pre-built reqwest
+    /// client.
+    #[cfg(feature = "blocking")]
+    pub fn with_blocking_client(key: String, client: reqwest::blocking::Client) -> SGClient {
+        SGClient::builder(key).blocking_client(client).build()
+    }
+
+    // The headers sent with every request, for a body of the given type. The mail send endpoint
+    // takes a form while the rest of the API takes JSON.
+    fn headers(&self, content_type: &'static str) -> SendgridResult<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.api_key))?,
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers.insert(USER_AGENT, HeaderValue::from_str(&self.user_agent)?);
+        if let Some(ref subuser) = self.on_behalf_of {
+            headers.insert(ON_BEHALF_OF_HEADER, HeaderValue::from_str(subuser)?);
+        }
+
+        Ok(headers)
+    }
+
+    // Refuse to upload a body that SendGrid is going to reject for its size.
+    fn check_payload_size(&self, size: usize) -> SendgridResult<()> {
+        if size > self.max_payload_size {
+            return Err(SendgridErrorKind::PayloadTooLarge(size, self.max_payload_size).into());
+        }
+        Ok(())
+    }
+
+    // Refuse to send a message whose attachments are over the configured limits.
+    fn check_attachments<M: Message>(&self, message: &M) -> SendgridResult<()> {
+        let sizes = message.attachment_sizes();
+        if sizes.len() > self.max_attachments {
+            let kind = SendgridErrorKind::TooManyAttachments(sizes.len(), self.max_attachments);
+            return Err(kind.into());
+        }
+        match sizes
+            .into_iter()
+            .find(|&(_, size)| size > self.max_attachment_size)
+        {
+            Some((filename, size)) => Err(SendgridErrorKind::AttachmentTooLarge(
+                filename.to_string(),
+                size,
+                self.max_attachment_size,
+            )
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    // SendGrid rejected a body for its size anyway, which is reported the same way as a body
+    // that was never sent, caused by the API error.
+    fn payload_too_large(&self, size: usize, res: RawResponse) -> SendgridError {
+        SendgridError::with_chain(
+            api_error(res.status, res.body),
+            SendgridErrorKind::PayloadTooLarge(size, self.max_payload_size),
+        )
+    }
+
+    // Prepare a request to the given path with the usual headers.
+    fn request(
+        &self,
+        method: Method,
+        path: &str,
+        content_type: &'static str,
+        body: Option<String>,
+    ) -> SendgridResult<PreparedRequest> {
+        Ok(PreparedRequest {
+            method,
+            url: make_url(&self.base_url, path),
+            headers: self.headers(content_type)?,
+            body,
+            timeout: self.timeout,
+        })
+    }
+
+    // Prepare the request that sends a message, with its idempotency key and extra headers if
+    // it has them. The extra headers can't replace the ones set by the client.
+    fn message_request<M: Message>(&self, message: M) -> SendgridResult<PreparedRequest> {
+        self.check_attachments(&message)?;
+        let (path, content_type) = (message.path(), message.content_type());
+        let mut extra = HeaderMap::new();
+        if let Some(key) = message.idempotency_key() {
+            extra.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(key)?);
+        }
+        for (name, value) in message.request_headers() {
+            extra.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        let mut req = self.request(Method::POST, path, content_type, Some(message.into_body()?))?;
+        for (name, value) in extra {
+            if let Some(name) = name {
+                req.headers.entry(name).or_insert(value);
+            }
+        }
+        Ok(req)
+    }
+
+    /// Returns the request that `send` would make for a message, without sending it, so the
+    /// exact method, URL, headers and body can be inspected or compared against a snapshot. The
+    /// message is checked against the limits of the client just as it is when sending. Unlike
+    /// sandbox mode, SendGrid is never contacted. The headers include the API key.
+    pub fn preview<M: Message>(&self, message: M) -> SendgridResult<PreparedRequest> {
+        let req = self.message_request(message)?;
+        self.check_payload_size(req.body.as_ref().map_or(0, String::len))?;
+        Ok(req)
+    }
+
+    /// Sends a messages through the SendGrid API. It takes a V2 `Mail` struct, which is sent
+    /// form encoded, or a V3 `SGMailV3`, which is sent as JSON to the V3 API. It returns the
+    /// parsed response from the API, or an error carrying the `errors` array SendGrid returned
+    /// if the message was not accepted. A 401 is reported as `SendgridErrorKind::Unauthorized`
+    /// and a 403 as `SendgridErrorKind::Forbidden`, both caused by the API error.
+    ///
+    /// This blocks the current thread until SendGrid responds. It is available with the
+    /// `blocking` feature, which is enabled by default.
+    #[cfg(feature = "blocking")]
+    pub fn send<M: Message>(&self, message: M) -> SendgridResult<SendResponse> {
+        let req = self.message_request(message)?;
+        self.post_with_retries(req)
+    }
+
+    /// Sends many messages, with at most `concurrency` requests in flight at a time, and returns
+    /// the result of each send in the same order as the messages. A message that fails doesn't
+    /// stop the others from being sent. Each send is retried and timed out just like `send`, so
+    /// lowering the concurrency is the way to stay within rate limits.
+    ///
+    /// This blocks the current thread until every message has been sent, using up to
+    /// `concurrency` threads. A concurrency of zero is treated as one.
+    #[cfg(feature = "blocking")]
+    pub fn send_all<M: Message + Send>(
+        &self,
+        messages: Vec<M>,
+        concurrency: usize,
+    ) -> Vec<SendgridResult<SendResponse>> {
+        let count = messages.len();
+        let queue = Mutex::new(messages.into_iter().enumerate());
+        let results = Mutex::new((0..count).map(|_| None).collect::<Vec<_>>());
+        thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, count.max(1)) {
+                scope.spawn(|| loop {
+                    let next = queue.lock().unwrap().next();
+                    let (i, message) = match next {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    let result = self.send(message);
+                    results.lock().unwrap()[i] = Some(result);
+                });
+            }
+        });
+
+        let results = results.into_inner().unwrap().into_iter();
+        results
+            .map(|result| result.expect("every message was sent"))
+            .collect()
+    }
+
+    /// Posts an arbitrary JSON body to an endpoint of the API, such as `v3/mail/send`, with the
+    /// same authentication, base URL and retries as `send`. This is an escape hatch for
+    /// features the crate doesn't model yet: nothing about the body or endpoint is checked, so a
+    /// typo in a field name is silently ignored by SendGrid or rejected with an API error.
+    #[cfg(feature = "blocking")]
+    pub fn send_raw(&self, body: Value, endpoint: &str) -> SendgridResult<SendResponse> {
+        let req = self.request(
+            Method::POST,
+            endpoint,
+            JSON_CONTENT_TYPE,
+            Some(body.to_string()),
+        )?;
+        self.post_with_retries(req)
+    }
+
+    // Post a request, retrying according to the retry policy. Every attempt sends exactly the
+    // same request, including its idempotency key.
+    #[cfg(feature = "blocking")]
+    fn post_with_retries(&self, req: PreparedRequest) -> SendgridResult<SendResponse> {
+        let size = req.body.as_ref().map_or(0, String::len);
+        self.check_payload_size(size)?;
+        let mut attempts = 0;
+        let mut rate_limited = false;
+        loop {
+            attempts += 1;
+            let res = self.transport.send(req.clone())?;
+            rate_limited |= res.status == 429;
+            if res.status == 413 {
+                return Err(self.payload_too_large(size, res));
+            }
+
+            if let Some(delay) = self.retry_delay(res.status, &res.headers, attempts) {
+                thread::sleep(delay);
+                continue;
+            }
+            let mut sent = parse_response(res.status, &res.headers, res.body, attempts)?;
+            sent.rate_limited = rate_limited;
+            return Ok(sent);
+        }
+    }
+
+    /// Sends a message through the SendGrid API without blocking. This behaves exactly like
+    /// `send`, but must be awaited from within a Tokio runtime.
+    ///
+    /// Dropping the returned future before it completes aborts the request and closes its
+    /// connection rather than returning it to the pool.
+    pub async fn send_async<M: Message>(&self, message: M) -> SendgridResult<SendResponse> {
+        let req = self.message_request(message)?;
+        self.post_with_retries_async(req).await
+    }
+
+    /// The asynchronous version of `send_raw`.
+    pub async fn send_raw_async(
+        &self,
+        body: Value,
+        endpoint: &str,
+    ) -> SendgridResult<SendResponse> {
+        let req = self.request(
+            Method::POST,
+            endpoint,
+            JSON_CONTENT_TYPE,
+            Some(body.to_string()),
+        )?;
+        self.post_with_retries_async(req).await
+    }
+
+    // The asynchronous version of `post_with_retries`.
+    async fn post_with_retries_async(&self, req: PreparedRequest) -> SendgridResult<SendResponse> {
+        let size = req.body.as_ref().map_or(0, String::len);
+        self.check_payload_size(size)?;
+        let mut attempts = 0;
+        let mut rate_limited = false;
+        loop {
+            attempts += 1;
+            let res = self.transport.send_async(req.clone()).await?;
+            rate_limited |= res.status == 429;
+            if res.status == 413 {
+                return Err(self.payload_too_large(size, res));
+            }
+
+            if let Some(delay) = self.retry_delay(res.status, &res.headers, attempts) {
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            let mut sent = parse_response(res.status, &res.headers, res.body, attempts)?;
+            sent.rate_limited = rate_limited;
+            return Ok(sent);
+        }
+    }
+
+    // Make a blocking request to a JSON endpoint of the V3 API, returning the status and body of
+    // the response.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn api_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> SendgridResult<(u16, String)> {
+        let req = self.request(method, path, JSON_CONTENT_TYPE, body)?;
+        let res = self.transport.send(req)?;
+        Ok((res.status, res.body))
+    }
+
+    // The asynchronous version of `api_request`.
+    pub(crate) async fn api_request_async(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> SendgridResult<(u16, String)> {
+        let req = self.request(method, path, JSON_CONTENT_TYPE, body)?;
+        let res = self.transport.send_async(req).await?;
+        Ok((res.status, res.body))
+    }
+
+    /// Asks SendGrid for a new batch ID. Scheduled V3 messages sent with this ID, using
+    /// `SGMailV3::set_batch_id`, can later be paused or cancelled together.
+    #[cfg(feature = "blocking")]
+    pub fn generate_batch_id(&self) -> SendgridResult<String> {
+        let (status, body) = self.api_request(Method::POST, BATCH_PATH, None)?;
+        let batch: BatchId = parse_api_response(status, body)?;
+        Ok(batch.batch_id)
+    }
+
+    /// The asynchronous version of `generate_batch_id`.
+    pub async fn generate_batch_id_async(&self) -> SendgridResult<String> {
+        let (status, body) = self
+            .api_request_async(Method::POST, BATCH_PATH, None)
+            .await?;
+        let batch: BatchId = parse_api_response(status, body)?;
+        Ok(batch.batch_id)
+    }
+
+    /// Cancels the scheduled sends of a batch, so none of its messages are delivered. If the
+    /// batch has already been sent, the error has the kind
+    /// `SendgridErrorKind::BatchNotScheduled`.
+    #[cfg(feature = "blocking")]
+    pub fn cancel_scheduled(&self, batch_id: &str) -> SendgridResult<ScheduledSend> {
+        self.set_scheduled(batch_id, ScheduledSendStatus::Cancel)
+    }
+
+    /// Pauses the scheduled sends of a batch until the pause is removed. Errors are the same as
+    /// for `cancel_scheduled`.
+    #[cfg(feature = "blocking")]
+    pub fn pause_scheduled(&self, batch_id: &str) -> SendgridResult<ScheduledSend> {
+        self.set_scheduled(batch_id, ScheduledSendStatus::Pause)
+    }
+
+    /// The asynchronous version of `cancel_scheduled`.
+    pub async fn cancel_scheduled_async(&self, batch_id: &str) -> SendgridResult<ScheduledSend> {
+        self.set_scheduled_async(batch_id, ScheduledSendStatus::Cancel)
+            .await
     }
 
-    /// Sends a messages through the SendGrid API. It takes a Mail struct as an
-    /// argument. It returns the string response from the API as JSON.
-    /// It sets the Content-Type to be application/x-www-form-urlencoded.
-    pub fn send(self, mail_info: Mail) -> SendgridResult<String> {
-        let client = Client::new();
-        let mut headers = Headers::new();
-        headers.set(Authorization(Bearer {
-            token: self.api_key.to_owned(),
-        }));
-        headers.set(ContentType::form_url_encoded());
-        headers.set(UserAgent::new("sendgrid-rs"));
+    /// The asynchronous version of `pause_scheduled`.
+    pub async fn pause_scheduled_async(&self, batch_id: &str) -> SendgridResult<ScheduledSend> {
+        self.set_scheduled_async(batch_id, ScheduledSendStatus::Pause)
+            .await
+    }
+
+    #[cfg(feature = "blocking")]
+    fn set_scheduled(
+        &self,
+        batch_id: &str,
+        status: ScheduledSendStatus,
+    ) -> SendgridResult<ScheduledSend> {
+        let body = serde_json::to_string(&ScheduledSend {
+            batch_id: String::from(batch_id),
+            status,
+        })?;
+        let (status, body) = self.api_request(Method::POST, SCHEDULED_SENDS_PATH, Some(body))?;
+        parse_scheduled_send(batch_id, status, body)
+    }
 
-        let post_body = make_post_body(mail_info)?;
-        let mut res = client
-            .post(API_URL)
-            .headers(headers)
-            .body(post_body)
-            .send()?;
-        let mut body = String::new();
-        res.read_to_string(&mut body)?;
-        Ok(body)
+    async fn set_scheduled_async(
+        &self,
+        batch_id: &str,
+        status: ScheduledSendStatus,
+    ) -> SendgridResult<ScheduledSend> {
+        let body = serde_json::to_string(&ScheduledSend {
+            batch_id: String::from(batch_id),
+            status,
+        })?;
+        let (status, body) = self
+            .api_request_async(Method::POST, SCHEDULED_SENDS_PATH, Some(body))
+            .await?;
+        parse_scheduled_send(batch_id, status, body)
     }
 }
 
@@ -109,14 +1172,794 @@ fn basic_message_body() {
     m.add_text("It works");
 
     let body = make_post_body(m);
-    let want = "to%5B%5D=test%40example.com&from=me%40example.com&subject=Test&\
-                html=&text=It+works&fromname=&replyto=&date=&headers=%7B%7D&x-smtpapi=";
+    let want = "to%5B%5D=test%40example.com&from=me%40example.com&subject=Test&text=It+works";
+    assert_eq!(body.unwrap(), want);
+}
+
+#[test]
+fn text_constructor_body() {
+    let m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+
+    let body = make_post_body(m);
+    let want = "to%5B%5D=test%40example.com&from=me%40example.com&subject=Test&text=It+works";
     assert_eq!(body.unwrap(), want);
 }
 
+#[test]
+fn recipient_names_stay_aligned() {
+    let mut m = Mail::new();
+    m.add_to(("a@example.com", "A"));
+    m.add_to("b@example.com");
+    m.add_cc("c@example.com");
+    m.add_from("me@example.com");
+    m.add_text("It works");
+
+    let body = make_post_body(m).unwrap();
+    let want = "to%5B%5D=a%40example.com&to%5B%5D=b%40example.com&toname%5B%5D=A&toname%5B%5D=&\
+                cc%5B%5D=c%40example.com&from";
+    assert!(body.starts_with(want), "{}", body);
+}
+
+#[test]
+fn empty_names_are_omitted() {
+    let mut m = Mail::new();
+    m.add_to("a@example.com");
+    m.add_cc(("c@example.com", ""));
+    m.add_bcc(Destination::new("d@example.com"));
+    m.add_from("me@example.com");
+    m.add_text("It works");
+
+    let body = make_post_body(m).unwrap();
+    let want = "to%5B%5D=a%40example.com&cc%5B%5D=c%40example.com&bcc%5B%5D=d%40example.com&from";
+    assert!(body.starts_with(want), "{}", body);
+}
+
+#[test]
+fn inline_image_body() {
+    let mut m = Mail::new();
+    m.add_html("<img src=\"cid:logo\">");
+    m.add_inline_image("logo", "logo.png", "image/png", b"png");
+
+    let body = make_post_body(m).unwrap();
+    assert!(
+        body.starts_with("files%5Blogo.png%5D=cG5n&content%5Blogo.png%5D=logo&"),
+        "{}",
+        body
+    );
+}
+
+#[test]
+fn attachment_form_keys() {
+    let mut m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    m.add_attachment_bytes("report.pdf", "application/pdf", b"pdf");
+    m.add_inline_image("logo", "logo.png", "image/png", b"png");
+
+    let body = make_post_body(m).unwrap();
+    let fields: Vec<(String, String)> = url::form_urlencoded::parse(body.as_bytes())
+        .into_owned()
+        .collect();
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    assert_eq!(field("files[report.pdf]"), Some("cGRm"));
+    assert_eq!(field("files[logo.png]"), Some("cG5n"));
+    assert_eq!(field("content[logo.png]"), Some("logo"));
+    assert_eq!(field("content[report.pdf]"), None);
+}
+
+#[test]
+fn utf8_body() {
+    let subject = "héllo 🎉 世界";
+    let m = Mail::text("test@example.com", subject, "me@example.com", subject);
+
+    let body = make_post_body(m).unwrap();
+    let encoded = "h%C3%A9llo+%F0%9F%8E%89+%E4%B8%96%E7%95%8C";
+    assert!(body.contains(&format!("&subject={}&", encoded)), "{}", body);
+    assert!(body.contains(&format!("&text={}", encoded)), "{}", body);
+
+    let decoded: std::collections::HashMap<_, _> =
+        url::form_urlencoded::parse(body.as_bytes()).collect();
+    assert_eq!(decoded["subject"], subject);
+    assert_eq!(decoded["text"], subject);
+}
+
+#[test]
+fn message_without_body() {
+    let mut m = Mail::new();
+    m.add_to("test@example.com");
+    m.add_from("me@example.com");
+    m.add_subject("Test");
+
+    match make_post_body(m) {
+        Err(SendgridError(SendgridErrorKind::MissingBody, _)) => {}
+        other => panic!("expected a missing body error, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_proper_key() {
     let want = "files[test.jpg]";
     let got = make_form_key("files", "test.jpg");
     assert_eq!(want, got);
 }
+
+#[test]
+fn test_make_url() {
+    let want = "https://api.eu.sendgrid.com/api/mail.send.json";
+    assert_eq!(
+        make_url("https://api.eu.sendgrid.com", MAIL_SEND_PATH),
+        want
+    );
+    assert_eq!(
+        make_url("https://api.eu.sendgrid.com/", MAIL_SEND_PATH),
+        want
+    );
+    assert_eq!(
+        make_url("https://api.eu.sendgrid.com/", "/api/mail.send.json"),
+        want
+    );
+}
+
+// Serve each of the given raw HTTP responses to one request in turn, returning the base URL of the
+// server.
+#[cfg(all(test, feature = "blocking"))]
+fn serve(responses: Vec<&'static str>) -> String {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            // Read the whole request so the client isn't reset before it sees the response.
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find(|line| line.starts_with("content-length:"))
+                        .and_then(|line| line[15..].trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    url
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_retries_server_errors() {
+    let url = serve(vec![
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Length: 21\r\nConnection: close\r\n\r\n{\"message\":\"success\"}",
+    ]);
+
+    let mut m = Mail::new();
+    m.add_to("test@example.com");
+    m.add_from("me@example.com");
+    m.add_text("It works");
+
+    let sg = SGClient::builder(String::from("key"))
+        .base_url(url)
+        .retries(RetryPolicy {
+            max_retries: 1,
+            base_delay: Duration::from_millis(10),
+        })
+        .build();
+    let res = sg.send(m).unwrap();
+    assert_eq!(res.attempts, 2);
+    assert_eq!(res.retry_count(), 1);
+    assert!(!res.rate_limited);
+    assert_eq!(res.message, Some(String::from("success")));
+    assert_eq!(res.rate_limit, None);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_returns_message_id() {
+    let url = serve(vec![
+        "HTTP/1.1 202 Accepted\r\nX-Message-Id: 14c5d75ce93\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ]);
+
+    let mut m = Mail::new();
+    m.add_to("test@example.com");
+    m.add_from("me@example.com");
+    m.add_text("It works");
+
+    let res = SGClient::with_base_url(String::from("key"), url)
+        .send(m)
+        .unwrap();
+    assert_eq!(res.status, 202);
+    assert_eq!(res.message_id, Some(String::from("14c5d75ce93")));
+}
+
+#[test]
+fn custom_user_agent() {
+    let sg = SGClient::new(String::from("key"));
+    assert_eq!(
+        sg.headers(FORM_CONTENT_TYPE).unwrap()[USER_AGENT],
+        "sendgrid-rs"
+    );
+
+    let sg = SGClient::builder(String::from("key"))
+        .user_agent("myapp/2.3 (sendgrid-rs)")
+        .build();
+    assert_eq!(
+        sg.headers(FORM_CONTENT_TYPE).unwrap()[USER_AGENT],
+        "myapp/2.3 (sendgrid-rs)"
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_through_proxy() {
+    let proxy = serve(vec![
+        "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ]);
+
+    let mut m = Mail::new();
+    m.add_to("test@example.com");
+    m.add_from("me@example.com");
+    m.add_text("It works");
+
+    // The base URL can't be resolved, so the send only succeeds if it goes through the proxy.
+    let sg = SGClient::builder(String::from("key"))
+        .base_url("http://sendgrid.invalid")
+        .proxy(reqwest::Proxy::http(&proxy).unwrap())
+        .build();
+    assert_eq!(sg.send(m).unwrap().status, 202);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn generate_batch_id() {
+    let url = serve(vec![
+        "HTTP/1.1 201 Created\r\nContent-Length: 32\r\nConnection: close\r\n\r\n\
+         {\"batch_id\":\"YOUR_BATCH_ID_123\"}",
+    ]);
+
+    let sg = SGClient::with_base_url(String::from("key"), url);
+    assert_eq!(sg.generate_batch_id().unwrap(), "YOUR_BATCH_ID_123");
+}
+
+#[test]
+fn parse_scheduled_send_response() {
+    let body = String::from(r#"{"batch_id":"abc","status":"pause"}"#);
+    let got = parse_scheduled_send("abc", 201, body).unwrap();
+    assert_eq!(got.status, ScheduledSendStatus::Pause);
+
+    let body = String::from(
+        r#"{"errors":[{"field":"batch_id","message":"The batch has already been sent"}]}"#,
+    );
+    match parse_scheduled_send("abc", 400, body) {
+        Err(SendgridError(SendgridErrorKind::BatchNotScheduled(ref id), _)) if id == "abc" => {}
+        other => panic!("expected the batch to be unscheduled, got {:?}", other),
+    }
+
+    let body = String::from(r#"{"errors":[{"field":"batch_id","message":"invalid batch id"}]}"#);
+    match parse_scheduled_send("abc", 400, body) {
+        Err(SendgridError(SendgridErrorKind::Api(400, _, _), _)) => {}
+        other => panic!(
+            "expected an API error for a malformed batch ID, got {:?}",
+            other
+        ),
+    }
+
+    let body = String::from(r#"{"errors":[{"field":null,"message":"authorization required"}]}"#);
+    match parse_scheduled_send("abc", 401, body) {
+        Err(SendgridError(SendgridErrorKind::Api(401, _, _), _)) => {}
+        other => panic!("expected an API error, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_with_mock_transport() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(503, "");
+    mock.push_response(200, r#"{"message":"success"}"#);
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .retries(RetryPolicy {
+            max_retries: 1,
+            base_delay: Duration::from_millis(1),
+        })
+        .build();
+
+    let res = sg
+        .send(Mail::text(
+            "test@example.com",
+            "Test",
+            "me@example.com",
+            "It works",
+        ))
+        .unwrap();
+    assert_eq!(res.attempts, 2);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0].url,
+        "https://api.sendgrid.com/api/mail.send.json"
+    );
+    assert_eq!(requests[0].headers[AUTHORIZATION], "Bearer key");
+    assert!(requests[1].body.as_ref().unwrap().contains("text=It+works"));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_raw_json() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(202, "");
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .build();
+
+    let body = serde_json::json!({"from": {"email": "me@example.com"}});
+    assert_eq!(sg.send_raw(body, "v3/mail/send").unwrap().status, 202);
+
+    let requests = mock.requests();
+    assert_eq!(requests[0].url, "https://api.sendgrid.com/v3/mail/send");
+    assert_eq!(requests[0].headers[CONTENT_TYPE], "application/json");
+    assert_eq!(
+        requests[0].body.as_deref(),
+        Some(r#"{"from":{"email":"me@example.com"}}"#)
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_v3_json() {
+    use crate::transport::MockTransport;
+    use crate::v3::{Content, Email, Personalization};
+
+    let mock = MockTransport::new();
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .build();
+
+    let mut m = SGMailV3::new();
+    let mut from = Email::new();
+    from.set_email("me@example.com");
+    m.set_from(from);
+    m.set_subject("Test");
+    let mut content = Content::new();
+    content.set_content_type("text/plain");
+    content.set_value("It works");
+    m.add_content(content);
+    let mut p = Personalization::new();
+    let mut to = Email::new();
+    to.set_email("test@example.com");
+    p.add_to(to);
+    m.add_personalization(p);
+    sg.send(&m).unwrap();
+
+    let v2 = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    sg.send(v2).unwrap();
+
+    let requests = mock.requests();
+    assert_eq!(requests[0].url, "https://api.sendgrid.com/v3/mail/send");
+    assert_eq!(requests[0].headers[CONTENT_TYPE], "application/json");
+    assert_eq!(requests[0].body, Some(serde_json::to_string(&m).unwrap()));
+    assert_eq!(
+        requests[1].url,
+        "https://api.sendgrid.com/api/mail.send.json"
+    );
+    assert_eq!(
+        requests[1].headers[CONTENT_TYPE],
+        "application/x-www-form-urlencoded; charset=utf-8"
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn retries_reuse_idempotency_key() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(503, "");
+    mock.push_response(200, r#"{"message":"success"}"#);
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .retries(RetryPolicy {
+            max_retries: 1,
+            base_delay: Duration::from_millis(1),
+        })
+        .build();
+
+    let mut m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    m.set_idempotency_key("order-1234");
+    assert_eq!(sg.send(m).unwrap().attempts, 2);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    for req in &requests {
+        assert_eq!(req.headers[IDEMPOTENCY_KEY_HEADER], "order-1234");
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn v3_idempotency_key() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .build();
+
+    let mut m = SGMailV3::new();
+    m.set_subject("Test");
+    m.set_idempotency_key("order-1234");
+    sg.send(&m).unwrap();
+    sg.send(m).unwrap();
+
+    let requests = mock.requests();
+    assert_eq!(requests[0].headers[IDEMPOTENCY_KEY_HEADER], "order-1234");
+    assert_eq!(requests[1].headers[IDEMPOTENCY_KEY_HEADER], "order-1234");
+    assert!(!requests[0].body.as_ref().unwrap().contains("order-1234"));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn v3_request_headers() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .build();
+
+    let mut m = SGMailV3::new();
+    m.set_subject("Test");
+    m.set_correlation_id("trace-42");
+    m.add_request_header("Authorization", "Bearer other");
+    sg.send(&m).unwrap();
+    sg.send(m).unwrap();
+
+    for req in &mock.requests() {
+        assert_eq!(req.headers["X-Correlation-Id"], "trace-42");
+        assert_eq!(req.headers[AUTHORIZATION], "Bearer key");
+        assert!(!req.body.as_ref().unwrap().contains("trace-42"));
+    }
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_reports_rate_limiting() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(202, "");
+    mock.push_response(429, "");
+    mock.push_response(202, "");
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock)
+        .retries(RetryPolicy {
+            max_retries: 1,
+            base_delay: Duration::from_millis(1),
+        })
+        .build();
+    let m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+
+    let res = sg.send(m.clone()).unwrap();
+    assert_eq!(res.retry_count(), 0);
+    assert!(!res.rate_limited);
+
+    let res = sg.send(m).unwrap();
+    assert_eq!(res.retry_count(), 1);
+    assert!(res.rate_limited);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_all_collects_results() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(400, r#"{"errors":["Bad recipient"]}"#);
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .build();
+
+    let base = Mail::text("a@example.com", "Test", "me@example.com", "It works");
+    let addresses = [
+        "a@example.com",
+        "b@example.com",
+        "c@example.com",
+        "d@example.com",
+    ];
+    let messages = addresses
+        .iter()
+        .map(|&to| base.with_recipient(to))
+        .collect();
+    let results = sg.send_all(messages, 2);
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+    assert_eq!(mock.requests().len(), 4);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_checks_attachment_limits() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .max_attachments(1)
+        .max_attachment_size(8)
+        .build();
+
+    let mut m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    m.add_attachment_bytes("big.bin", "application/octet-stream", b"too large");
+    match sg.send(m) {
+        Err(SendgridError(SendgridErrorKind::AttachmentTooLarge(ref name, 12, 8), _)) => {
+            assert_eq!(name, "big.bin")
+        }
+        other => panic!("expected the attachment to be too large, got {:?}", other),
+    }
+
+    let mut m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    m.add_attachment_bytes("a.txt", "text/plain", b"a");
+    m.add_attachment_bytes("b.txt", "text/plain", b"b");
+    match sg.send(m) {
+        Err(SendgridError(SendgridErrorKind::TooManyAttachments(2, 1), _)) => {}
+        other => panic!("expected too many attachments, got {:?}", other),
+    }
+    assert!(mock.requests().is_empty());
+}
+
+#[test]
+fn missing_api_key() {
+    let err = api_key_from_env("SENDGRID_RS_TEST_UNSET_KEY").unwrap_err();
+    assert!(err.is_kind(&SendgridErrorKind::MissingApiKey(String::from(
+        "SENDGRID_RS_TEST_UNSET_KEY"
+    ))));
+}
+
+#[test]
+fn on_behalf_of_header() {
+    let sg = SGClient::new(String::from("key"));
+    let m = || Mail::text("test@example.com", "Test", "me@example.com", "It works");
+
+    let req = sg.on_behalf_of("tenant-1").preview(m()).unwrap();
+    assert_eq!(req.headers[ON_BEHALF_OF_HEADER], "tenant-1");
+
+    let req = sg.preview(m()).unwrap();
+    assert!(req.headers.get(ON_BEHALF_OF_HEADER).is_none());
+}
+
+#[test]
+fn request_headers() {
+    let sg = SGClient::new(String::from("key"));
+    let mut m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    m.set_correlation_id("trace-42");
+    m.add_request_header("Authorization", "Bearer other");
+
+    let req = sg.preview(m).unwrap();
+    assert_eq!(req.headers["X-Correlation-Id"], "trace-42");
+    assert_eq!(req.headers[AUTHORIZATION], "Bearer key");
+}
+
+#[test]
+fn preview_request() {
+    let sg = SGClient::new(String::from("key"));
+    let m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    let req = sg.preview(m).unwrap();
+
+    assert_eq!(req.method, Method::POST);
+    assert_eq!(req.url, "https://api.sendgrid.com/api/mail.send.json");
+    assert_eq!(
+        req.header_pairs(),
+        vec![
+            (String::from("authorization"), String::from("Bearer key")),
+            (
+                String::from("content-type"),
+                String::from(FORM_CONTENT_TYPE)
+            ),
+            (String::from("user-agent"), String::from(DEFAULT_USER_AGENT)),
+        ]
+    );
+    assert_eq!(
+        req.body.as_deref(),
+        Some("to%5B%5D=test%40example.com&from=me%40example.com&subject=Test&text=It+works")
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_rejects_large_payloads() {
+    use crate::transport::MockTransport;
+
+    let mock = MockTransport::new();
+    mock.push_response(413, "");
+    let sg = SGClient::builder(String::from("key"))
+        .transport(mock.clone())
+        .max_payload_size(1000)
+        .build();
+
+    let mut m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    m.add_attachment_bytes("big.bin", "application/octet-stream", &[0; 1000]);
+    match sg.send(m) {
+        Err(SendgridError(SendgridErrorKind::PayloadTooLarge(_, 1000), _)) => {}
+        other => panic!("expected the payload to be too large, got {:?}", other),
+    }
+    assert!(mock.requests().is_empty());
+
+    let m = Mail::text("test@example.com", "Test", "me@example.com", "It works");
+    match sg.send(m) {
+        Err(SendgridError(SendgridErrorKind::PayloadTooLarge(_, 1000), _)) => {}
+        other => panic!("expected the payload to be too large, got {:?}", other),
+    }
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[test]
+fn retry_delay_backs_off() {
+    let policy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(100),
+    };
+    let mut headers = HeaderMap::new();
+    assert_eq!(policy.delay(1, &headers), Duration::from_millis(100));
+    assert_eq!(policy.delay(3, &headers), Duration::from_millis(400));
+
+    headers.insert("Retry-After", HeaderValue::from_static("5"));
+    assert_eq!(policy.delay(1, &headers), Duration::from_secs(5));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn send_times_out() {
+    use std::net::TcpListener;
+
+    // Accept the connection but never respond to it.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let mut m = Mail::new();
+    m.add_to("test@example.com");
+    m.add_from("me@example.com");
+    m.add_text("It works");
+
+    let sg =
+        SGClient::with_base_url(String::from("key"), url).with_timeout(Duration::from_millis(100));
+    match sg.send(m) {
+        Err(SendgridError(SendgridErrorKind::Timeout, _)) => {}
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_success_response() {
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Message-Id", HeaderValue::from_static("abc123"));
+    headers.insert("X-RateLimit-Limit", HeaderValue::from_static("600"));
+    headers.insert("X-RateLimit-Remaining", HeaderValue::from_static("599"));
+    headers.insert("X-RateLimit-Reset", HeaderValue::from_static("1700000000"));
+
+    let body = String::from(r#"{"message":"success"}"#);
+    let res = parse_response(200, &headers, body, 1).unwrap();
+    assert_eq!(res.status, 200);
+    assert_eq!(res.message_id, Some(String::from("abc123")));
+    assert_eq!(res.message, Some(String::from("success")));
+    assert!(res.is_success());
+    assert_eq!(
+        res.rate_limit,
+        Some(RateLimit {
+            limit: 600,
+            remaining: 599,
+            reset: 1_700_000_000,
+        })
+    );
+}
+
+#[test]
+fn parse_success_body() {
+    let body = String::from(r#"{"message":"success","new_field":1}"#);
+    let res = parse_response(200, &HeaderMap::new(), body, 1).unwrap();
+    assert_eq!(
+        res.body,
+        SuccessBody {
+            message: Some(String::from("success")),
+            warnings: Vec::new(),
+        }
+    );
+    assert!(res.body.is_success());
+
+    let res = parse_response(202, &HeaderMap::new(), String::new(), 1).unwrap();
+    assert_eq!(res.status, 202);
+    assert_eq!(res.body, SuccessBody::default());
+    assert!(!res.is_success());
+}
+
+#[test]
+fn parse_response_warnings() {
+    let body = String::from(r#"{"message":"success","warnings":["-name- was not replaced"]}"#);
+    let res = parse_response(200, &HeaderMap::new(), body, 1).unwrap();
+    assert_eq!(res.warnings, vec![String::from("-name- was not replaced")]);
+
+    let body = String::from(r#"{"message":"success"}"#);
+    let res = parse_response(200, &HeaderMap::new(), body, 1).unwrap();
+    assert!(res.warnings.is_empty());
+}
+
+#[test]
+fn parse_error_response() {
+    let body = String::from(r#"{"message":"error","errors":["Empty to"]}"#);
+    match parse_response(400, &HeaderMap::new(), body.clone(), 1) {
+        Err(e) => match *e.kind() {
+            SendgridErrorKind::Api(status, ref errors, ref raw_body) => {
+                assert_eq!(status, 400);
+                assert_eq!(errors[0].message, "Empty to");
+                assert_eq!(errors[0].field, None);
+                assert_eq!(raw_body, &body);
+            }
+            _ => panic!("unexpected error kind"),
+        },
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn parse_auth_error_response() {
+    let body = String::from(r#"{"errors":[{"field":null,"message":"bad key"}]}"#);
+    let err = parse_response(401, &HeaderMap::new(), body.clone(), 1).unwrap_err();
+    assert!(err.is_kind(&SendgridErrorKind::Unauthorized));
+    assert_eq!(err.status(), Some(401));
+
+    let err = parse_response(403, &HeaderMap::new(), body, 1).unwrap_err();
+    assert!(err.is_kind(&SendgridErrorKind::Forbidden));
+    assert_eq!(err.status(), Some(403));
+}
+
+#[test]
+fn parse_v3_error_response() {
+    let body = String::from(
+        r#"{"errors":[{"message":"Does not contain a valid address.","field":"from.email","help":null}]}"#,
+    );
+    match parse_response(400, &HeaderMap::new(), body, 1) {
+        Err(e) => match *e.kind() {
+            SendgridErrorKind::Api(_, ref errors, _) => {
+                assert_eq!(errors[0].message, "Does not contain a valid address.");
+                assert_eq!(errors[0].field, Some(String::from("from.email")));
+                assert_eq!(errors[0].help, None);
+            }
+            _ => panic!("unexpected error kind"),
+        },
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn drop_client_in_runtime() {
+    // Building and dropping a client from async code must not start a blocking client, whose
+    // runtime can't be dropped there.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let sg = SGClient::new(String::from("key"));
+        drop(sg);
+    });
+}
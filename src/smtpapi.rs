@@ -0,0 +1,163 @@
+use crate::errors::{SendgridErrorKind, SendgridResult};
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+/// A typed representation of the X-SMTPAPI header used by the V2 API. It is serialized to JSON
+/// when the message is sent, so there is no need to build or escape the JSON by hand.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SmtpApiHeader {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    category: Vec<String>,
+
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    sub: HashMap<String, Vec<String>>,
+
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    section: HashMap<String, String>,
+
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    unique_args: HashMap<String, String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_at: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    asm_group_id: Option<u64>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    asm_groups_to_display: Vec<u64>,
+
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    filters: HashMap<String, Filter>,
+}
+
+// The settings of one of SendGrid's apps, such as legacy templates, in the form the filters
+// object expects.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct Filter {
+    settings: BTreeMap<String, Value>,
+}
+
+impl SmtpApiHeader {
+    /// Construct a new, empty X-SMTPAPI header.
+    pub fn new() -> SmtpApiHeader {
+        SmtpApiHeader::default()
+    }
+
+    /// Add a category to tag the message with in SendGrid's statistics.
+    pub fn add_category<T: Into<String>>(&mut self, category: T) {
+        self.category.push(category.into());
+    }
+
+    /// Add a substitution tag along with the values to replace it with. Each value is used for
+    /// the recipient at the same position in the message.
+    pub fn add_substitution<S, I, T>(&mut self, tag: S, values: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.sub
+            .insert(tag.into(), values.into_iter().map(Into::into).collect());
+    }
+
+    /// Add a section, which is a block of content that substitutions can refer to.
+    pub fn add_section<T: Into<String>, U: Into<String>>(&mut self, tag: T, value: U) {
+        self.section.insert(tag.into(), value.into());
+    }
+
+    /// Add an argument that is passed back with events for this message.
+    pub fn add_unique_arg<T: Into<String>, U: Into<String>>(&mut self, key: T, value: U) {
+        self.unique_args.insert(key.into(), value.into());
+    }
+
+    /// Set the time to deliver the message at, as a Unix timestamp.
+    pub fn set_send_at(&mut self, timestamp: i64) {
+        self.send_at = Some(timestamp);
+    }
+
+    /// Set the unsubscribe group the message belongs to. The group must already exist in the
+    /// SendGrid account.
+    pub fn set_asm_group_id(&mut self, group_id: u64) {
+        self.asm_group_id = Some(group_id);
+    }
+
+    /// Set the unsubscribe groups shown to a recipient on the subscription management page.
+    pub fn set_asm_groups_to_display(&mut self, group_ids: Vec<u64>) {
+        self.asm_groups_to_display = group_ids;
+    }
+
+    /// Apply a legacy template to the message, turning on the templates app with
+    /// `{"templates": {"settings": {"enable": 1, "template_id": ...}}}` in the filters. These
+    /// are the templates made before dynamic templates, as dynamic templates need the V3 API.
+    pub fn set_template<T: Into<String>>(&mut self, template_id: T) {
+        self.set_filter_setting("templates", "enable", Value::from(1));
+        self.set_filter_setting("templates", "template_id", Value::from(template_id.into()));
+    }
+
+    // Set one of the settings of an app, creating its filter if needed.
+    fn set_filter_setting(&mut self, app: &str, setting: &str, value: Value) {
+        let filter = self.filters.entry(app.to_string()).or_default();
+        filter.settings.insert(setting.to_string(), value);
+    }
+
+    /// Whether anything has been set on the header.
+    pub fn is_empty(&self) -> bool {
+        *self == SmtpApiHeader::default()
+    }
+
+    /// Check that every substitution has one value per recipient.
+    pub fn check_substitutions(&self, recipients: usize) -> SendgridResult<()> {
+        for (tag, values) in &self.sub {
+            if values.len() != recipients {
+                return Err(SendgridErrorKind::SubstitutionMismatch(
+                    tag.clone(),
+                    values.len(),
+                    recipients,
+                )
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Used internally for string encoding. Not needed for message building.
+    pub fn to_json(&self) -> SendgridResult<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+#[test]
+fn smtpapi_json() {
+    let mut header = SmtpApiHeader::new();
+    header.add_category("receipts");
+    header.add_substitution("-name-", vec!["Alice", "Bob"]);
+    header.add_unique_arg("user_id", "42");
+    header.set_send_at(1_700_000_000);
+    header.set_asm_group_id(7);
+    header.set_asm_groups_to_display(vec![7, 8]);
+
+    let got: serde_json::Value = serde_json::from_str(&header.to_json().unwrap()).unwrap();
+    let want = serde_json::json!({
+        "category": ["receipts"],
+        "sub": {"-name-": ["Alice", "Bob"]},
+        "unique_args": {"user_id": "42"},
+        "send_at": 1_700_000_000,
+        "asm_group_id": 7,
+        "asm_groups_to_display": [7, 8],
+    });
+    assert_eq!(got, want);
+}
+
+#[test]
+fn template_filter_json() {
+    let mut header = SmtpApiHeader::new();
+    header.set_template("5997fcf6-2b9f-484d-acd5-7e9a99f0dc1f");
+    assert_eq!(
+        header.to_json().unwrap(),
+        r#"{"filters":{"templates":{"settings":{"enable":1,"template_id":"5997fcf6-2b9f-484d-acd5-7e9a99f0dc1f"}}}}"#
+    );
+}
@@ -0,0 +1,158 @@
+//! Access to the global email statistics of the account.
+
+use crate::errors::SendgridResult;
+use crate::sg_client::{parse_api_response, SGClient};
+
+use reqwest::Method;
+use url::form_urlencoded::Serializer;
+
+static STATS_PATH: &str = "v3/stats";
+
+/// How statistics are grouped into buckets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregatedBy {
+    /// One bucket per day.
+    Day,
+    /// One bucket per week.
+    Week,
+    /// One bucket per month.
+    Month,
+}
+
+impl AggregatedBy {
+    // The value of the query parameter.
+    fn as_str(self) -> &'static str {
+        match self {
+            AggregatedBy::Day => "day",
+            AggregatedBy::Week => "week",
+            AggregatedBy::Month => "month",
+        }
+    }
+}
+
+/// The counts of what happened to messages during a period. Each field is the metric of the same
+/// name in SendGrid's documentation, and is zero if SendGrid left it out.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Metrics {
+    pub blocks: u64,
+    pub bounce_drops: u64,
+    pub bounces: u64,
+    pub clicks: u64,
+    pub deferred: u64,
+    pub delivered: u64,
+    pub invalid_emails: u64,
+    pub opens: u64,
+    pub processed: u64,
+    pub requests: u64,
+    pub spam_report_drops: u64,
+    pub spam_reports: u64,
+    pub unique_clicks: u64,
+    pub unique_opens: u64,
+    pub unsubscribe_drops: u64,
+    pub unsubscribes: u64,
+}
+
+/// The statistics for one period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsBucket {
+    /// The first day of the period, as `YYYY-MM-DD`.
+    pub date: String,
+    /// The counts for the period.
+    pub metrics: Metrics,
+}
+
+// SendGrid nests the metrics of each period in a list, which only has one entry for the global
+// statistics.
+#[derive(Deserialize)]
+struct RawBucket {
+    date: String,
+    stats: Vec<RawStat>,
+}
+
+#[derive(Deserialize)]
+struct RawStat {
+    metrics: Metrics,
+}
+
+impl From<RawBucket> for StatsBucket {
+    fn from(raw: RawBucket) -> StatsBucket {
+        StatsBucket {
+            date: raw.date,
+            metrics: raw
+                .stats
+                .into_iter()
+                .next()
+                .map(|stat| stat.metrics)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// The path for a statistics query. The dates are passed through as given.
+fn stats_path(start_date: &str, end_date: &str, aggregated_by: AggregatedBy) -> String {
+    let query = Serializer::new(String::new())
+        .append_pair("start_date", start_date)
+        .append_pair("end_date", end_date)
+        .append_pair("aggregated_by", aggregated_by.as_str())
+        .finish();
+    format!("{}?{}", STATS_PATH, query)
+}
+
+// Decode the response to a statistics query.
+fn parse_stats(status: u16, raw_body: String) -> SendgridResult<Vec<StatsBucket>> {
+    let raw: Vec<RawBucket> = parse_api_response(status, raw_body)?;
+    Ok(raw.into_iter().map(StatsBucket::from).collect())
+}
+
+impl SGClient {
+    /// Fetches the statistics for every message sent between two dates, inclusive, grouped into
+    /// buckets. Dates are formatted as `YYYY-MM-DD`, which a chrono date produces with
+    /// `date.format("%Y-%m-%d")`.
+    #[cfg(feature = "blocking")]
+    pub fn get_stats(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        aggregated_by: AggregatedBy,
+    ) -> SendgridResult<Vec<StatsBucket>> {
+        let path = stats_path(start_date, end_date, aggregated_by);
+        let (status, body) = self.api_request(Method::GET, &path, None)?;
+        parse_stats(status, body)
+    }
+
+    /// The asynchronous version of `get_stats`.
+    pub async fn get_stats_async(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        aggregated_by: AggregatedBy,
+    ) -> SendgridResult<Vec<StatsBucket>> {
+        let path = stats_path(start_date, end_date, aggregated_by);
+        let (status, body) = self.api_request_async(Method::GET, &path, None).await?;
+        parse_stats(status, body)
+    }
+}
+
+#[test]
+fn test_stats_path() {
+    assert_eq!(
+        stats_path("2024-01-01", "2024-01-31", AggregatedBy::Week),
+        "v3/stats?start_date=2024-01-01&end_date=2024-01-31&aggregated_by=week"
+    );
+}
+
+#[test]
+fn parse_stats_response() {
+    let body = String::from(
+        r#"[{"date": "2024-01-01", "stats": [{"metrics": {"requests": 10, "delivered": 9,
+             "bounces": 1, "opens": 4, "unique_opens": 3}}]},
+            {"date": "2024-01-02", "stats": []}]"#,
+    );
+    let got = parse_stats(200, body).unwrap();
+    assert_eq!(got.len(), 2);
+    assert_eq!(got[0].date, "2024-01-01");
+    assert_eq!(got[0].metrics.delivered, 9);
+    assert_eq!(got[0].metrics.unique_opens, 3);
+    assert_eq!(got[1].metrics, Metrics::default());
+}
@@ -0,0 +1,289 @@
+//! Access to the suppression lists SendGrid keeps of addresses it won't deliver to, and the
+//! unsubscribe groups recipients can opt out of.
+
+use crate::errors::SendgridResult;
+use crate::sg_client::{check_api_response, parse_api_response, SGClient};
+
+use reqwest::Method;
+use url::form_urlencoded::byte_serialize;
+
+static BOUNCES_PATH: &str = "v3/suppression/bounces";
+static GROUPS_PATH: &str = "v3/asm/groups";
+
+/// An address that bounced, which SendGrid won't send to again until it is removed from the
+/// list.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Bounce {
+    /// The address that bounced.
+    pub email: String,
+    /// When the address bounced, as a Unix timestamp in seconds.
+    pub created: i64,
+    /// The response the receiving server gave, such as "550 5.1.1 User unknown".
+    pub reason: String,
+    /// The enhanced SMTP status code of the bounce, such as "5.1.1".
+    pub status: String,
+}
+
+impl Bounce {
+    /// A best-effort guess at why the address bounced, from the reason or, failing that, the
+    /// status code.
+    pub fn category(&self) -> BounceCategory {
+        match BounceCategory::from_reason(&self.reason) {
+            BounceCategory::Unknown(reason) => match BounceCategory::from_reason(&self.status) {
+                BounceCategory::Unknown(_) => BounceCategory::Unknown(reason),
+                category => category,
+            },
+            category => category,
+        }
+    }
+}
+
+/// The broad cause of a bounce, block or spam report, guessed from the free form reason the
+/// receiving server gave. Servers word their responses differently, so this is best effort and
+/// falls back to `Unknown`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BounceCategory {
+    /// The address doesn't exist at the domain.
+    MailboxNotFound,
+    /// The mailbox exists but is full, which usually clears up on its own.
+    MailboxFull,
+    /// The domain doesn't exist or doesn't accept mail.
+    DomainNotFound,
+    /// The receiving server refused the message because of a policy, such as spam filtering or
+    /// the reputation of the sending IP.
+    Blocked,
+    /// The message was too large for the receiving server.
+    MessageTooLarge,
+    /// The reason didn't match a known category. This holds the original reason.
+    Unknown(String),
+}
+
+impl BounceCategory {
+    /// Classify a reason such as "550 5.1.1 User unknown". The enhanced status codes for unknown
+    /// mailboxes and domains are checked before the wording, since they are more precise and
+    /// many servers give little else.
+    pub fn from_reason(reason: &str) -> BounceCategory {
+        let lower = reason.to_lowercase();
+        let mentions = |phrases: &[&str]| phrases.iter().any(|phrase| lower.contains(phrase));
+
+        if mentions(&["5.1.2", "5.4.4"]) {
+            BounceCategory::DomainNotFound
+        } else if mentions(&["5.1.1"]) {
+            BounceCategory::MailboxNotFound
+        } else if mentions(&[
+            "mailbox full",
+            "quota",
+            "insufficient storage",
+            "5.2.2",
+            "4.2.2",
+        ]) {
+            BounceCategory::MailboxFull
+        } else if mentions(&[
+            "message too large",
+            "message size",
+            "too big",
+            "5.3.4",
+            "5.2.3",
+        ]) {
+            BounceCategory::MessageTooLarge
+        } else if mentions(&[
+            "user unknown",
+            "unknown user",
+            "no such user",
+            "does not exist",
+            "mailbox not found",
+            "mailbox unavailable",
+            "invalid recipient",
+        ]) {
+            BounceCategory::MailboxNotFound
+        } else if mentions(&["domain not found", "host not found", "unrouteable", "no mx"]) {
+            BounceCategory::DomainNotFound
+        } else if mentions(&[
+            "blocked",
+            "spam",
+            "blacklist",
+            "blocklist",
+            "policy",
+            "5.7.",
+        ]) {
+            BounceCategory::Blocked
+        } else {
+            BounceCategory::Unknown(reason.to_string())
+        }
+    }
+
+    /// Whether sending to the address again is pointless. A full mailbox may accept mail later,
+    /// and a block may be lifted, so those aren't permanent, and neither is an unknown reason.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            *self,
+            BounceCategory::MailboxNotFound | BounceCategory::DomainNotFound
+        )
+    }
+}
+
+/// An unsubscribe group, which lets recipients opt out of one kind of email while still receiving
+/// the rest.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct UnsubscribeGroup {
+    /// The ID of the group, as passed to `Mail::set_asm_group`.
+    pub id: u64,
+    /// The name of the group, which is shown to recipients.
+    pub name: String,
+    /// A description of the group, which is shown to recipients.
+    pub description: String,
+    /// Whether messages are put in this group when they don't name one.
+    pub is_default: bool,
+}
+
+// The path for a single address on a suppression list. Addresses can hold characters such as `+`
+// that have to be escaped.
+fn address_path(list: &str, email: &str) -> String {
+    format!(
+        "{}/{}",
+        list,
+        byte_serialize(email.as_bytes()).collect::<String>()
+    )
+}
+
+impl SGClient {
+    /// Lists every address on the bounce list.
+    #[cfg(feature = "blocking")]
+    pub fn list_bounces(&self) -> SendgridResult<Vec<Bounce>> {
+        let (status, body) = self.api_request(Method::GET, BOUNCES_PATH, None)?;
+        parse_api_response(status, body)
+    }
+
+    /// Looks up a single address on the bounce list, returning `None` if it isn't there.
+    #[cfg(feature = "blocking")]
+    pub fn get_bounce(&self, email: &str) -> SendgridResult<Option<Bounce>> {
+        let path = address_path(BOUNCES_PATH, email);
+        let (status, body) = self.api_request(Method::GET, &path, None)?;
+        let bounces: Vec<Bounce> = parse_api_response(status, body)?;
+        Ok(bounces.into_iter().next())
+    }
+
+    /// Removes an address from the bounce list, so messages will be sent to it again.
+    #[cfg(feature = "blocking")]
+    pub fn delete_bounce(&self, email: &str) -> SendgridResult<()> {
+        let path = address_path(BOUNCES_PATH, email);
+        let (status, body) = self.api_request(Method::DELETE, &path, None)?;
+        check_api_response(status, body)
+    }
+
+    /// Lists the unsubscribe groups of the account.
+    #[cfg(feature = "blocking")]
+    pub fn list_unsubscribe_groups(&self) -> SendgridResult<Vec<UnsubscribeGroup>> {
+        let (status, body) = self.api_request(Method::GET, GROUPS_PATH, None)?;
+        parse_api_response(status, body)
+    }
+
+    /// The asynchronous version of `list_bounces`.
+    pub async fn list_bounces_async(&self) -> SendgridResult<Vec<Bounce>> {
+        let (status, body) = self
+            .api_request_async(Method::GET, BOUNCES_PATH, None)
+            .await?;
+        parse_api_response(status, body)
+    }
+
+    /// The asynchronous version of `get_bounce`.
+    pub async fn get_bounce_async(&self, email: &str) -> SendgridResult<Option<Bounce>> {
+        let path = address_path(BOUNCES_PATH, email);
+        let (status, body) = self.api_request_async(Method::GET, &path, None).await?;
+        let bounces: Vec<Bounce> = parse_api_response(status, body)?;
+        Ok(bounces.into_iter().next())
+    }
+
+    /// The asynchronous version of `list_unsubscribe_groups`.
+    pub async fn list_unsubscribe_groups_async(&self) -> SendgridResult<Vec<UnsubscribeGroup>> {
+        let (status, body) = self
+            .api_request_async(Method::GET, GROUPS_PATH, None)
+            .await?;
+        parse_api_response(status, body)
+    }
+
+    /// The asynchronous version of `delete_bounce`.
+    pub async fn delete_bounce_async(&self, email: &str) -> SendgridResult<()> {
+        let path = address_path(BOUNCES_PATH, email);
+        let (status, body) = self.api_request_async(Method::DELETE, &path, None).await?;
+        check_api_response(status, body)
+    }
+}
+
+#[test]
+fn test_address_path() {
+    assert_eq!(
+        address_path(BOUNCES_PATH, "a+tag@example.com"),
+        "v3/suppression/bounces/a%2Btag%40example.com"
+    );
+}
+
+#[test]
+fn parse_bounces() {
+    let body = String::from(
+        r#"[{"created": 1443651125, "email": "a@example.com",
+             "reason": "550 5.1.1 User unknown", "status": "5.1.1"}]"#,
+    );
+    let bounces: Vec<Bounce> = parse_api_response(200, body).unwrap();
+    assert_eq!(
+        bounces,
+        vec![Bounce {
+            email: String::from("a@example.com"),
+            created: 1443651125,
+            reason: String::from("550 5.1.1 User unknown"),
+            status: String::from("5.1.1"),
+        }]
+    );
+}
+
+#[test]
+fn parse_unsubscribe_groups() {
+    let body = String::from(
+        r#"[{"id": 100, "name": "Newsletters", "description": "Our monthly newsletter",
+             "is_default": true, "unsubscribes": 400}]"#,
+    );
+    let groups: Vec<UnsubscribeGroup> = parse_api_response(200, body).unwrap();
+    assert_eq!(groups[0].id, 100);
+    assert_eq!(groups[0].name, "Newsletters");
+    assert!(groups[0].is_default);
+}
+
+#[test]
+fn bounce_categories() {
+    let cases = [
+        ("550 5.1.1 User unknown", BounceCategory::MailboxNotFound),
+        (
+            "452 4.2.2 The email account is over quota",
+            BounceCategory::MailboxFull,
+        ),
+        (
+            "550 5.7.1 Message rejected due to spam content",
+            BounceCategory::Blocked,
+        ),
+        ("Domain not found", BounceCategory::DomainNotFound),
+        (
+            "550 5.1.2 Domain does not exist",
+            BounceCategory::DomainNotFound,
+        ),
+        (
+            "552 Message size exceeds fixed limit",
+            BounceCategory::MessageTooLarge,
+        ),
+        (
+            "Something odd",
+            BounceCategory::Unknown(String::from("Something odd")),
+        ),
+    ];
+    for (reason, category) in cases.iter() {
+        assert_eq!(BounceCategory::from_reason(reason), *category, "{}", reason);
+    }
+
+    let bounce = Bounce {
+        email: String::from("a@example.com"),
+        created: 0,
+        reason: String::from("Rejected"),
+        status: String::from("5.1.1"),
+    };
+    assert_eq!(bounce.category(), BounceCategory::MailboxNotFound);
+    assert!(bounce.category().is_permanent());
+}
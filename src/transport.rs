@@ -0,0 +1,287 @@
+//! The layer that carries requests to SendGrid. `SGClient` prepares every request and hands it
+//! to a `Transport`, which by default makes a real HTTP request with reqwest. Tests can use a
+//! `MockTransport` instead to inspect requests without a network.
+
+use crate::errors::{SendgridError, SendgridErrorKind, SendgridResult};
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+#[cfg(feature = "blocking")]
+use std::sync::OnceLock;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::{Client, Method};
+
+/// A future returned by `Transport::send_async`.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A request to the SendGrid API, with everything needed to send it.
+#[derive(Clone, Debug)]
+pub struct PreparedRequest {
+    /// The HTTP method of the request.
+    pub method: Method,
+    /// The full URL of the request.
+    pub url: String,
+    /// The headers of the request, including the `Authorization` header with the API key.
+    pub headers: HeaderMap,
+    /// The body of the request, if it has one.
+    pub body: Option<String>,
+    /// How long the request may take before it is abandoned.
+    pub timeout: Duration,
+}
+
+impl PreparedRequest {
+    /// The headers of the request as name and value pairs, sorted by name, for comparing a
+    /// request against a snapshot. Values that aren't valid UTF-8 are converted lossily.
+    pub fn header_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs: Vec<_> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.as_str().to_string(), value)
+            })
+            .collect();
+        pairs.sort();
+        pairs
+    }
+}
+
+/// A response from the SendGrid API, before it has been interpreted.
+#[derive(Clone, Debug)]
+pub struct RawResponse {
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The headers of the response.
+    pub headers: HeaderMap,
+    /// The body of the response.
+    pub body: String,
+}
+
+/// Sends prepared requests to SendGrid. Errors should only be returned when no response was
+/// received, since the client interprets error statuses itself.
+///
+/// Only `send_async` has to be implemented. The blocking `send` is used by the client's blocking
+/// methods, which need the `blocking` feature, and by default it drives `send_async` to
+/// completion on the current thread.
+pub trait Transport: Send + Sync {
+    /// Sends a request, blocking the current thread until the response arrives. The default
+    /// implementation polls `send_async` without an async runtime, so transports whose futures
+    /// need one, such as those built on Tokio, should override it.
+    fn send(&self, request: PreparedRequest) -> SendgridResult<RawResponse> {
+        block_on(self.send_async(request))
+    }
+
+    /// Sends a request without blocking.
+    fn send_async(&self, request: PreparedRequest) -> BoxFuture<'_, SendgridResult<RawResponse>>;
+}
+
+// Wakes a future that is being driven by `block_on` by unparking its thread.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Poll a future on the current thread until it completes, parking the thread while it waits.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+// Timeouts are surfaced as their own error kind so callers can tell them apart from other
+// failures.
+fn request_error(e: reqwest::Error) -> SendgridError {
+    if e.is_timeout() {
+        SendgridError::with_chain(e, SendgridErrorKind::Timeout)
+    } else {
+        e.into()
+    }
+}
+
+// The default transport, which makes real HTTP requests with reqwest. The blocking client is
+// only built on the first blocking send, since it runs a runtime of its own that panics when
+// dropped from within an async context.
+pub(crate) struct ReqwestTransport {
+    pub(crate) client: Client,
+    #[cfg(feature = "blocking")]
+    pub(crate) blocking_client: OnceLock<reqwest::blocking::Client>,
+    #[cfg(feature = "blocking")]
+    pub(crate) build_blocking_client: Box<dyn Fn() -> reqwest::blocking::Client + Send + Sync>,
+}
+
+impl Transport for ReqwestTransport {
+    #[cfg(feature = "blocking")]
+    fn send(&self, request: PreparedRequest) -> SendgridResult<RawResponse> {
+        let mut req = self
+            .blocking_client
+            .get_or_init(|| (self.build_blocking_client)())
+            .request(request.method, &request.url)
+            .headers(request.headers)
+            .timeout(request.timeout);
+        if let Some(body) = request.body {
+            req = req.body(body);
+        }
+        let res = req.send().map_err(request_error)?;
+
+        let status = res.status().as_u16();
+        let headers = res.headers().clone();
+        let body = res.text().map_err(request_error)?;
+        Ok(RawResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    fn send_async(&self, request: PreparedRequest) -> BoxFuture<'_, SendgridResult<RawResponse>> {
+        Box::pin(async move {
+            let mut req = self
+                .client
+                .request(request.method, &request.url)
+                .headers(request.headers)
+                .timeout(request.timeout);
+            if let Some(body) = request.body {
+                req = req.body(body);
+            }
+            let res = req.send().await.map_err(request_error)?;
+
+            let status = res.status().as_u16();
+            let headers = res.headers().clone();
+            let body = res.text().await.map_err(request_error)?;
+            Ok(RawResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+// The state shared between the clones of a mock transport.
+#[derive(Default)]
+struct MockState {
+    requests: Vec<PreparedRequest>,
+    responses: VecDeque<RawResponse>,
+}
+
+/// A transport that records requests and answers them with canned responses, for testing code
+/// that uses an `SGClient`. Clones share their state, so a clone can be given to the client and
+/// the original used to inspect what was sent.
+///
+/// # Examples
+///
+/// ```
+/// # use sendgrid::mail::Mail;
+/// # use sendgrid::sg_client::SGClient;
+/// # use sendgrid::transport::MockTransport;
+/// let mock = MockTransport::new();
+/// mock.push_response(202, "");
+/// let sg = SGClient::builder(String::from("key"))
+///     .transport(mock.clone())
+///     .build();
+///
+/// # #[cfg(feature = "blocking")]
+/// sg.send(Mail::text("a@example.com", "Hi", "me@example.com", "Hello")).unwrap();
+/// # #[cfg(feature = "blocking")]
+/// assert!(mock.requests()[0].body.as_ref().unwrap().contains("text=Hello"));
+/// ```
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTransport {
+    /// Constructs a mock transport with no canned responses.
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Queues a response with the given status and body. Responses are used in the order they
+    /// were queued, and once they run out every request gets an empty 200 response.
+    pub fn push_response(&self, status: u16, body: &str) {
+        self.push_raw_response(RawResponse {
+            status,
+            headers: HeaderMap::new(),
+            body: String::from(body),
+        });
+    }
+
+    /// Queues a response, such as one with headers.
+    pub fn push_raw_response(&self, response: RawResponse) {
+        self.state.lock().unwrap().responses.push_back(response);
+    }
+
+    /// The requests sent so far, in order.
+    pub fn requests(&self) -> Vec<PreparedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    // Record a request and pick its response.
+    fn respond(&self, request: PreparedRequest) -> RawResponse {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(request);
+        state.responses.pop_front().unwrap_or_else(|| RawResponse {
+            status: 200,
+            headers: HeaderMap::new(),
+            body: String::new(),
+        })
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: PreparedRequest) -> SendgridResult<RawResponse> {
+        Ok(self.respond(request))
+    }
+
+    fn send_async(&self, request: PreparedRequest) -> BoxFuture<'_, SendgridResult<RawResponse>> {
+        let response = self.respond(request);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[test]
+fn default_blocking_send() {
+    // A transport that only implements the asynchronous method.
+    struct AsyncOnly;
+
+    impl Transport for AsyncOnly {
+        fn send_async(
+            &self,
+            request: PreparedRequest,
+        ) -> BoxFuture<'_, SendgridResult<RawResponse>> {
+            Box::pin(async move {
+                Ok(RawResponse {
+                    status: 202,
+                    headers: HeaderMap::new(),
+                    body: request.url,
+                })
+            })
+        }
+    }
+
+    let request = PreparedRequest {
+        method: Method::POST,
+        url: String::from("https://api.sendgrid.com/v3/mail/send"),
+        headers: HeaderMap::new(),
+        body: None,
+        timeout: Duration::from_secs(1),
+    };
+    let response = AsyncOnly.send(request).unwrap();
+    assert_eq!(response.status, 202);
+    assert_eq!(response.body, "https://api.sendgrid.com/v3/mail/send");
+}
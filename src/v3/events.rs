@@ -0,0 +1,274 @@
+//! Parsing for the delivery events SendGrid posts to the event webhook.
+
+use crate::errors::SendgridResult;
+
+use std::collections::HashMap;
+
+#[cfg(feature = "p256")]
+use data_encoding::BASE64;
+use serde_json::Value;
+
+/// The header holding the signature of a signed event webhook request.
+pub static SIGNATURE_HEADER: &str = "X-Twilio-Email-Event-Webhook-Signature";
+/// The header holding the timestamp of a signed event webhook request.
+pub static TIMESTAMP_HEADER: &str = "X-Twilio-Email-Event-Webhook-Timestamp";
+
+/// The fields SendGrid includes with every event.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct EventCommon {
+    /// The address of the recipient the event is about.
+    pub email: String,
+    /// When the event happened, as a Unix timestamp in seconds.
+    pub timestamp: i64,
+    /// A unique ID for the event, which can be used to drop duplicate deliveries of the webhook.
+    pub sg_event_id: String,
+    /// The ID of the message the event is about. This starts with the `X-Message-Id` returned
+    /// by the send, but SendGrid appends more to it.
+    pub sg_message_id: Option<String>,
+    /// Every other field of the event, including any custom arguments and categories.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// An event from the event webhook, with the fields that are specific to its type.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    /// SendGrid accepted the message and is going to deliver it.
+    Processed(EventCommon),
+    /// The receiving server refused the message for now, so delivery will be retried.
+    Deferred {
+        #[serde(flatten)]
+        common: EventCommon,
+        response: Option<String>,
+        attempt: Option<String>,
+    },
+    /// The receiving server accepted the message.
+    Delivered {
+        #[serde(flatten)]
+        common: EventCommon,
+        response: Option<String>,
+    },
+    /// The recipient opened the message.
+    Open {
+        #[serde(flatten)]
+        common: EventCommon,
+        useragent: Option<String>,
+        ip: Option<String>,
+    },
+    /// The recipient clicked a link in the message.
+    Click {
+        #[serde(flatten)]
+        common: EventCommon,
+        url: String,
+        useragent: Option<String>,
+        ip: Option<String>,
+    },
+    /// The receiving server rejected the message permanently.
+    Bounce {
+        #[serde(flatten)]
+        common: EventCommon,
+        reason: Option<String>,
+        status: Option<String>,
+        #[serde(rename = "type")]
+        bounce_type: Option<String>,
+    },
+    /// SendGrid didn't send the message, such as because the address is suppressed.
+    Dropped {
+        #[serde(flatten)]
+        common: EventCommon,
+        reason: Option<String>,
+    },
+    /// The recipient marked the message as spam.
+    SpamReport(EventCommon),
+    /// The recipient unsubscribed from all email.
+    Unsubscribe(EventCommon),
+    /// The recipient unsubscribed from an unsubscribe group.
+    #[serde(rename = "group_unsubscribe")]
+    GroupUnsubscribe {
+        #[serde(flatten)]
+        common: EventCommon,
+        asm_group_id: Option<u64>,
+    },
+    /// The recipient resubscribed to an unsubscribe group.
+    #[serde(rename = "group_resubscribe")]
+    GroupResubscribe {
+        #[serde(flatten)]
+        common: EventCommon,
+        asm_group_id: Option<u64>,
+    },
+    /// An event of a type this crate doesn't know about yet. Its fields are discarded.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Event {
+    /// The fields shared by every event, or `None` for an unknown event.
+    pub fn common(&self) -> Option<&EventCommon> {
+        match *self {
+            Event::Processed(ref common)
+            | Event::SpamReport(ref common)
+            | Event::Unsubscribe(ref common)
+            | Event::Deferred { ref common, .. }
+            | Event::Delivered { ref common, .. }
+            | Event::Open { ref common, .. }
+            | Event::Click { ref common, .. }
+            | Event::Bounce { ref common, .. }
+            | Event::Dropped { ref common, .. }
+            | Event::GroupUnsubscribe { ref common, .. }
+            | Event::GroupResubscribe { ref common, .. } => Some(common),
+            Event::Unknown => None,
+        }
+    }
+}
+
+/// Decodes the body of an event webhook request, which is a JSON array of events.
+pub fn parse_events(body: &[u8]) -> SendgridResult<Vec<Event>> {
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Checks the signature of a signed event webhook request. The public key is the base64 value
+/// shown in the SendGrid dashboard, the payload is the raw request body, and the signature and
+/// timestamp are the values of `SIGNATURE_HEADER` and `TIMESTAMP_HEADER`. Anything that can't be
+/// decoded is treated as an invalid signature. The payload must be exactly the bytes SendGrid
+/// sent, so verify it before parsing it.
+///
+/// This is available with the `p256` feature.
+#[cfg(feature = "p256")]
+pub fn verify_signature(
+    public_key: &str,
+    payload: &[u8],
+    signature: &str,
+    timestamp: &str,
+) -> bool {
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::{Signature, VerifyingKey};
+    use p256::pkcs8::DecodePublicKey;
+
+    let key = BASE64
+        .decode(public_key.trim().as_bytes())
+        .ok()
+        .and_then(|der| VerifyingKey::from_public_key_der(&der).ok());
+    let signature = BASE64
+        .decode(signature.trim().as_bytes())
+        .ok()
+        .and_then(|der| Signature::from_der(&der).ok());
+    let (key, signature) = match (key, signature) {
+        (Some(key), Some(signature)) => (key, signature),
+        _ => return false,
+    };
+
+    // The signature may use either of the two equivalent S values, but only the low one verifies.
+    let signature = signature.normalize_s().unwrap_or(signature);
+    let mut signed = timestamp.as_bytes().to_vec();
+    signed.extend_from_slice(payload);
+    key.verify(&signed, &signature).is_ok()
+}
+
+// The example from SendGrid's own libraries.
+#[cfg(feature = "p256")]
+#[test]
+fn verify_sendgrid_signature() {
+    let public_key =
+        "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEEDr2LjtURuePQzplybdC+u4CwrqDqBaWjcMMsTbhdbcwHBc\
+                      epxo7yAQGhHPTnlvFYPAZFceEu/1FwCM/QmGUhA==";
+    let signature =
+        "MEUCIQCtIHJeH93Y+qpYeWrySphQgpNGNr/U+UyUlBkU6n7RAwIgJTz2C+8a8xonZGi6BpSzoQsbVRam\
+                     r2nlxFDWYNH2j/0=";
+    let payload =
+        br#"{"category":"example_payload","event":"test_event","message_id":"message_id"}"#;
+    assert!(verify_signature(
+        public_key,
+        payload,
+        signature,
+        "1588788367"
+    ));
+    assert!(!verify_signature(
+        public_key,
+        payload,
+        signature,
+        "1588788368"
+    ));
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn verify_webhook_signature() {
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::pkcs8::EncodePublicKey;
+
+    let signing_key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let public_key = signing_key.verifying_key().to_public_key_der().unwrap();
+    let public_key = BASE64.encode(public_key.as_bytes());
+
+    let payload = br#"[{"email":"a@example.com","event":"processed"}]"#;
+    let timestamp = "1588788367";
+    let signed = [timestamp.as_bytes(), &payload[..]].concat();
+    let signature: Signature = signing_key.sign(&signed);
+    let signature = BASE64.encode(&signature.to_der().to_bytes());
+
+    assert!(verify_signature(
+        &public_key,
+        payload,
+        &signature,
+        timestamp
+    ));
+    assert!(!verify_signature(&public_key, b"[]", &signature, timestamp));
+    assert!(!verify_signature(
+        &public_key,
+        payload,
+        &signature,
+        "1588788368"
+    ));
+    assert!(!verify_signature(
+        "not a key",
+        payload,
+        &signature,
+        timestamp
+    ));
+    assert!(!verify_signature(
+        &public_key,
+        payload,
+        "not a signature",
+        timestamp
+    ));
+}
+
+#[test]
+fn parse_webhook_events() {
+    let body = br#"[
+        {"email": "a@example.com", "timestamp": 1513299569, "event": "click",
+         "sg_event_id": "e1", "sg_message_id": "m1.filter", "url": "https://example.com",
+         "user_id": "42"},
+        {"email": "b@example.com", "timestamp": 1513299570, "event": "bounce",
+         "sg_event_id": "e2", "reason": "550 No such user", "status": "5.1.1", "type": "bounce"},
+        {"email": "c@example.com", "timestamp": 1513299571, "event": "spamreport",
+         "sg_event_id": "e3"},
+        {"email": "d@example.com", "timestamp": 1513299572, "event": "something_new",
+         "sg_event_id": "e4"}
+    ]"#;
+
+    let events = parse_events(body).unwrap();
+    assert_eq!(events.len(), 4);
+    match events[0] {
+        Event::Click {
+            ref common,
+            ref url,
+            ..
+        } => {
+            assert_eq!(url, "https://example.com");
+            assert_eq!(common.sg_message_id.as_deref(), Some("m1.filter"));
+            assert_eq!(common.extra["user_id"], "42");
+        }
+        ref other => panic!("expected a click, got {:?}", other),
+    }
+    match events[1] {
+        Event::Bounce { ref reason, .. } => {
+            assert_eq!(reason.as_deref(), Some("550 No such user"))
+        }
+        ref other => panic!("expected a bounce, got {:?}", other),
+    }
+    assert_eq!(events[2].common().unwrap().email, "c@example.com");
+    assert_eq!(events[3], Event::Unknown);
+}
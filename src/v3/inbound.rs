@@ -0,0 +1,276 @@
+//! Parsing for the payloads SendGrid's Inbound Parse webhook posts when an email arrives at a
+//! configured domain.
+
+use crate::errors::{SendgridErrorKind, SendgridResult};
+
+use std::collections::HashMap;
+
+/// A file attached to an inbound email.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InboundAttachment {
+    /// The name of the file, as given by the sender.
+    pub filename: String,
+    /// The MIME type of the file, such as "application/pdf".
+    pub content_type: String,
+    /// The raw contents of the file.
+    pub data: Vec<u8>,
+}
+
+/// An email received by the Inbound Parse webhook. All text is decoded as UTF-8, with invalid
+/// sequences replaced, so check `fields["charsets"]` if senders may use other encodings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InboundEmail {
+    /// The sender, in mailbox form such as `Alice <alice@example.com>`.
+    pub from: String,
+    /// The recipients, as they appeared in the To header.
+    pub to: String,
+    /// The subject of the email.
+    pub subject: String,
+    /// The plain text body, which is empty if the email had none.
+    pub text: String,
+    /// The HTML body, which is empty if the email had none.
+    pub html: String,
+    /// The attachments, in the order SendGrid sent them.
+    pub attachments: Vec<InboundAttachment>,
+    /// Every other field SendGrid sent, such as `headers`, `envelope` and `spam_score`.
+    pub fields: HashMap<String, String>,
+}
+
+impl InboundEmail {
+    /// Parses the body of an Inbound Parse request. The content type is the value of the
+    /// request's `Content-Type` header, which holds the multipart boundary.
+    pub fn from_multipart(content_type: &str, body: &[u8]) -> SendgridResult<InboundEmail> {
+        let boundary = header_param(content_type, "boundary")
+            .ok_or_else(|| invalid("the content type has no boundary"))?;
+        let mut email = InboundEmail::default();
+
+        for part in split_parts(body, &boundary)? {
+            let (headers, data) = parse_part(part)?;
+            let disposition = headers
+                .get("content-disposition")
+                .ok_or_else(|| invalid("a part has no content disposition"))?;
+            let name =
+                header_param(disposition, "name").ok_or_else(|| invalid("a part has no name"))?;
+
+            if let Some(filename) = header_param(disposition, "filename") {
+                email.attachments.push(InboundAttachment {
+                    filename,
+                    content_type: headers
+                        .get("content-type")
+                        .cloned()
+                        .unwrap_or_else(|| String::from("application/octet-stream")),
+                    data: data.to_vec(),
+                });
+                continue;
+            }
+
+            let value = String::from_utf8_lossy(data).into_owned();
+            match name.as_str() {
+                "from" => email.from = value,
+                "to" => email.to = value,
+                "subject" => email.subject = value,
+                "text" => email.text = value,
+                "html" => email.html = value,
+                _ => {
+                    email.fields.insert(name, value);
+                }
+            }
+        }
+
+        Ok(email)
+    }
+}
+
+// The error for a body that is not valid multipart form data.
+fn invalid(reason: &str) -> SendgridErrorKind {
+    SendgridErrorKind::InvalidMultipart(String::from(reason))
+}
+
+// Find the position of a byte string within another, starting from an offset.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
+// Split a multipart body into its parts, each still holding its own headers. Anything before the
+// first delimiter or after the closing one is ignored, as RFC 2046 requires.
+fn split_parts<'a>(body: &'a [u8], boundary: &str) -> SendgridResult<Vec<&'a [u8]>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let next = [&b"\r\n"[..], &delimiter[..]].concat();
+    let mut parts = Vec::new();
+
+    let mut start =
+        find(body, &delimiter, 0).ok_or_else(|| invalid("no parts were found"))? + delimiter.len();
+    loop {
+        if body[start..].starts_with(b"--") {
+            return Ok(parts);
+        }
+        if !body[start..].starts_with(b"\r\n") {
+            return Err(invalid("a delimiter isn't followed by a line break").into());
+        }
+        start += 2;
+
+        let end = find(body, &next, start).ok_or_else(|| invalid("the body is truncated"))?;
+        parts.push(&body[start..end]);
+        start = end + next.len();
+    }
+}
+
+// Split the headers of a part from its contents. Header names are lowercased.
+fn parse_part(part: &[u8]) -> SendgridResult<(HashMap<String, String>, &[u8])> {
+    // A part with no headers starts with the blank line.
+    let (head, data) = if part.starts_with(b"\r\n") {
+        (&b""[..], &part[2..])
+    } else {
+        let end = find(part, b"\r\n\r\n", 0).ok_or_else(|| invalid("a part has no headers"))?;
+        (&part[..end], &part[end + 4..])
+    };
+
+    let mut headers = HashMap::new();
+    for line in String::from_utf8_lossy(head).split("\r\n") {
+        if let Some(colon) = line.find(':') {
+            headers.insert(
+                line[..colon].trim().to_lowercase(),
+                line[colon + 1..].trim().to_string(),
+            );
+        }
+    }
+
+    Ok((headers, data))
+}
+
+// Read a parameter such as `name="text"` from a header value, removing any quotes.
+fn header_param(value: &str, param: &str) -> Option<String> {
+    split_params(value).into_iter().skip(1).find_map(|pair| {
+        let mut kv = pair.splitn(2, '=');
+        let key = kv.next()?.trim();
+        let value = kv.next()?.trim();
+        if key.eq_ignore_ascii_case(param) {
+            Some(unquote(value))
+        } else {
+            None
+        }
+    })
+}
+
+// Split a header value on the semicolons between its parameters, leaving those inside quoted
+// strings alone.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if !quoted && c == ';' {
+            params.push(&value[start..i]);
+            start = i + 1;
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+// Remove the quotes around a parameter value, along with the backslashes escaping characters
+// inside them.
+fn unquote(value: &str) -> String {
+    let inner = match value.strip_prefix('"') {
+        Some(inner) => inner,
+        None => return value.to_string(),
+    };
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            '"' => break,
+            _ => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+#[test]
+fn parse_inbound_email() {
+    let body = "preamble\r\n\
+                --xYzZY\r\n\
+                Content-Disposition: form-data; name=\"from\"\r\n\r\n\
+                Alice <alice@example.com>\r\n\
+                --xYzZY\r\n\
+                Content-Disposition: form-data; name=\"to\"\r\n\r\n\
+                reply@parse.example.com\r\n\
+                --xYzZY\r\n\
+                Content-Disposition: form-data; name=\"subject\"\r\n\r\n\
+                Re: Hello\r\n\
+                --xYzZY\r\n\
+                Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+                Thanks!\r\n\r\n> quoted\r\n\
+                --xYzZY\r\n\
+                Content-Disposition: form-data; name=\"spam_score\"\r\n\r\n\
+                0.1\r\n\
+                --xYzZY\r\n\
+                Content-Disposition: form-data; name=\"attachment1\"; filename=\"a.bin\"\r\n\
+                Content-Type: application/octet-stream\r\n\r\n\
+                \x00\x01\r\n\
+                --xYzZY--\r\n";
+
+    let email =
+        InboundEmail::from_multipart("multipart/form-data; boundary=xYzZY", body.as_bytes())
+            .unwrap();
+    assert_eq!(email.from, "Alice <alice@example.com>");
+    assert_eq!(email.to, "reply@parse.example.com");
+    assert_eq!(email.subject, "Re: Hello");
+    assert_eq!(email.text, "Thanks!\r\n\r\n> quoted");
+    assert_eq!(email.html, "");
+    assert_eq!(email.fields["spam_score"], "0.1");
+    assert_eq!(
+        email.attachments,
+        vec![InboundAttachment {
+            filename: String::from("a.bin"),
+            content_type: String::from("application/octet-stream"),
+            data: vec![0, 1],
+        }]
+    );
+}
+
+#[test]
+fn quoted_header_params() {
+    let disposition = r#"form-data; name="attachment1"; filename="a;b \"c\".pdf""#;
+    assert_eq!(
+        header_param(disposition, "name").as_deref(),
+        Some("attachment1")
+    );
+    assert_eq!(
+        header_param(disposition, "filename").as_deref(),
+        Some(r#"a;b "c".pdf"#)
+    );
+    assert_eq!(
+        header_param("multipart/form-data; boundary=xYzZY", "boundary").as_deref(),
+        Some("xYzZY")
+    );
+}
+
+#[test]
+fn parse_invalid_multipart() {
+    let truncated = b"--xYzZY\r\nContent-Disposition: form-data; name=\"text\"\r\n\r\nHi";
+    for (content_type, body) in &[
+        ("multipart/form-data", &truncated[..]),
+        ("multipart/form-data; boundary=\"xYzZY\"", &truncated[..]),
+        ("multipart/form-data; boundary=other", &truncated[..]),
+    ] {
+        match InboundEmail::from_multipart(content_type, body) {
+            Err(crate::errors::SendgridError(SendgridErrorKind::InvalidMultipart(_), _)) => {}
+            other => panic!("expected an invalid multipart error, got {:?}", other),
+        }
+    }
+}
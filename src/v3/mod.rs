@@ -0,0 +1,1191 @@
+use crate::errors::{SendgridErrorKind, SendgridResult};
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "blocking")]
+use reqwest::blocking::Client;
+#[cfg(feature = "blocking")]
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
+
+use crate::mail::{normalize_base64, Destination, Disposition};
+
+use data_encoding::BASE64;
+use serde_json::Value;
+
+pub mod events;
+pub mod inbound;
+
+#[cfg(feature = "blocking")]
+pub use reqwest::blocking::Response;
+
+#[cfg(feature = "blocking")]
+use crate::sg_client::{make_url, API_URL, V3_MAIL_SEND_PATH};
+
+/// The most recipients SendGrid accepts on one message, counting every to, cc and bcc address
+/// across all of its personalizations.
+pub const MAX_RECIPIENTS: usize = 1000;
+
+/// The furthest in the future SendGrid accepts a message being scheduled for.
+pub const MAX_SCHEDULE_AHEAD: Duration = Duration::from_secs(72 * 60 * 60);
+
+/// Just a redefinition of a map to store string keys and values.
+pub type SGMap = HashMap<String, String>;
+
+/// Used to send a V3 message body. The underlying HTTP client is reused between sends.
+/// It is available with the `blocking` feature, which is enabled by default.
+///
+/// This sends the message as it is, without the timeout, User-Agent, retries or validation an
+/// `SGClient` applies. `SGClient::send` accepts an `SGMailV3` and should be used instead.
+#[cfg(feature = "blocking")]
+#[deprecated(
+    since = "0.7.1",
+    note = "use SGClient::send with an SGMailV3, which applies the client's timeout, User-Agent and validation"
+)]
+pub struct V3Sender {
+    api_key: String,
+    base_url: String,
+    client: Client,
+}
+
+/// The main structure for a V3 API mail send call. This is composed of many other smaller
+/// structures used to add lots of customization to your message.
+#[derive(Serialize)]
+pub struct SGMailV3 {
+    from: Email,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    subject: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<Email>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to_list: Option<Vec<Email>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    content: Vec<Content>,
+
+    personalizations: Vec<Personalization>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom_args: Option<SGMap>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_at: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ip_pool_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    asm: Option<Asm>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attachments: Option<Vec<Attachment>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mail_settings: Option<MailSettings>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tracking_settings: Option<TrackingSettings>,
+
+    #[serde(skip)]
+    idempotency_key: Option<String>,
+
+    #[serde(skip)]
+    request_headers: SGMap,
+}
+
+// Settings that change how SendGrid handles a message. Only the settings that were set are
+// sent, so the account defaults apply for the rest.
+#[derive(Default, Serialize)]
+struct MailSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sandbox_mode: Option<Setting>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bypass_list_management: Option<Setting>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    footer: Option<Footer>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spam_check: Option<SpamCheck>,
+}
+
+// A footer appended to the content of a message.
+#[derive(Serialize)]
+struct Footer {
+    enable: bool,
+    text: String,
+    html: String,
+}
+
+// Checks the message for spam before sending it, optionally posting the report to a URL.
+#[derive(Serialize)]
+struct SpamCheck {
+    enable: bool,
+    threshold: u8,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_to_url: Option<String>,
+}
+
+// Settings for tracking how recipients interact with a message. As with the mail settings, only
+// the settings that were set are sent.
+#[derive(Default, Serialize)]
+struct TrackingSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    click_tracking: Option<ClickTracking>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    open_tracking: Option<Setting>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription_tracking: Option<Setting>,
+}
+
+// The unsubscribe group of a message, and the groups shown on the subscription management page.
+#[derive(Serialize)]
+struct Asm {
+    group_id: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    groups_to_display: Vec<u64>,
+}
+
+// Click tracking can be turned on separately for the HTML and plain text content.
+#[derive(Serialize)]
+struct ClickTracking {
+    enable: bool,
+    enable_text: bool,
+}
+
+// A setting that can only be turned on or off.
+#[derive(Serialize)]
+struct Setting {
+    enable: bool,
+}
+
+/// An email with a required address and an optional name field.
+#[derive(Clone, Serialize)]
+pub struct Email {
+    email: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+/// The body of an email with the content type and the message.
+#[derive(Clone, Serialize)]
+pub struct Content {
+    #[serde(rename = "type")]
+    content_type: String,
+    value: String,
+}
+
+/// A personalization block for a V3 message. It has to at least contain one email as a to
+/// address. All other fields are optional.
+#[derive(Serialize)]
+pub struct Personalization {
+    to: Vec<Email>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cc: Option<Vec<Email>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bcc: Option<Vec<Email>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<SGMap>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    substitutions: Option<SGMap>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom_args: Option<SGMap>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_at: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dynamic_template_data: Option<Value>,
+}
+
+/// An attachment block for a V3 message. Content and filename are required. If the
+/// mime_type is unspecified, the email will use Sendgrid's default for attachments
+/// which is 'application/octet-stream'.
+///
+/// SendGrid builds the MIME part of each attachment itself from these fields, and the API has
+/// no way to add other headers to it, such as `Content-Description`.
+#[derive(Serialize)]
+pub struct Attachment {
+    content: String,
+
+    filename: String,
+
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disposition: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_id: Option<String>,
+}
+
+#[cfg(feature = "blocking")]
+#[allow(deprecated)]
+impl V3Sender {
+    /// Construct a new V3 message sender.
+    pub fn new(api_key: String) -> V3Sender {
+        V3Sender {
+            api_key,
+            base_url: String::from(API_URL),
+            client: Client::new(),
+        }
+    }
+
+    /// Construct a V3 message sender that sends to the given base URL instead of the default
+    /// US endpoint, such as a mock server in tests. Messages are posted to `v3/mail/send` under
+    /// it, just like `SGClient` does.
+    pub fn with_base_url<T: Into<String>>(api_key: String, base_url: T) -> V3Sender {
+        V3Sender {
+            api_key,
+            base_url: base_url.into(),
+            client: Client::new(),
+        }
+    }
+
+    /// Send a V3 message and return the status code or an error from the request.
+    pub fn send(&self, mail: &SGMailV3) -> SendgridResult<Response> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.api_key))?,
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(USER_AGENT, HeaderValue::from_static("sendgrid-rs"));
+
+        let body = mail.gen_json();
+        let res = self
+            .client
+            .post(make_url(&self.base_url, V3_MAIL_SEND_PATH))
+            .headers(headers)
+            .body(body)
+            .send()?;
+        Ok(res)
+    }
+}
+
+impl Default for SGMailV3 {
+    fn default() -> SGMailV3 {
+        SGMailV3::new()
+    }
+}
+
+impl SGMailV3 {
+    /// Construct a new V3 message.
+    pub fn new() -> SGMailV3 {
+        SGMailV3 {
+            from: Email::new(),
+            subject: String::new(),
+            reply_to: None,
+            reply_to_list: None,
+            content: Vec::new(),
+            personalizations: Vec::new(),
+            template_id: None,
+            custom_args: None,
+            send_at: None,
+            batch_id: None,
+            ip_pool_name: None,
+            asm: None,
+            attachments: None,
+            mail_settings: None,
+            tracking_settings: None,
+            idempotency_key: None,
+            request_headers: HashMap::new(),
+        }
+    }
+
+    /// Set the from address. This can be an `Email` or a `Destination`, so that the display
+    /// name can be chosen separately from the address. The name is left out of the message if
+    /// there isn't one.
+    pub fn set_from<T: Into<Email>>(&mut self, from: T) {
+        self.from = from.into();
+    }
+
+    /// Set the single reply-to address, replacing any added with `add_reply_to`.
+    pub fn set_reply_to(&mut self, reply_to: Email) {
+        self.reply_to = Some(reply_to);
+        self.reply_to_list = None;
+    }
+
+    /// Add a reply-to address. SendGrid rejects messages with both a `reply_to` and a
+    /// `reply_to_list`, so a single address is sent as `reply_to` and the list is only used
+    /// once there are more.
+    pub fn add_reply_to(&mut self, reply_to: Email) {
+        match (self.reply_to.take(), self.reply_to_list.as_mut()) {
+            (_, Some(list)) => list.push(reply_to),
+            (Some(first), None) => self.reply_to_list = Some(vec![first, reply_to]),
+            (None, None) => self.reply_to = Some(reply_to),
+        }
+    }
+
+    /// Set the subject. It can be left out when the message uses a dynamic template with its own
+    /// subject, or when every personalization sets one, and is then omitted from the request.
+    pub fn set_subject(&mut self, subject: &str) {
+        self.subject = String::from(subject);
+    }
+
+    /// Add content to the message.
+    pub fn add_content(&mut self, content: Content) {
+        self.content.push(content);
+    }
+
+    /// Add a part of the message body with the given MIME type, such as a `text/calendar`
+    /// invite. Unlike an attachment, the part is shown as part of the message. SendGrid requires
+    /// `text/plain` to come first and `text/html` next, so parts are kept in that order
+    /// regardless of when they were added.
+    pub fn add_content_part(&mut self, content_type: &str, value: &str) {
+        // Where a content type has to appear in the list, with lower ranks first.
+        fn rank(content_type: &str) -> u8 {
+            match content_type {
+                "text/plain" => 0,
+                "text/html" => 1,
+                _ => 2,
+            }
+        }
+
+        let mut content = Content::new();
+        content.set_content_type(content_type);
+        content.set_value(value);
+        let at = self
+            .content
+            .iter()
+            .position(|c| rank(&c.content_type) > rank(content_type))
+            .unwrap_or(self.content.len());
+        self.content.insert(at, content);
+    }
+
+    /// Send the message using a dynamic template. The template provides the content, so none has
+    /// to be added to the message.
+    pub fn set_template_id(&mut self, template_id: &str) {
+        self.template_id = Some(String::from(template_id));
+    }
+
+    /// Add a custom argument to the message. Custom arguments aren't shown to recipients, but
+    /// are included in the event webhook for every recipient. SendGrid merges these with the
+    /// custom arguments of each personalization, and the personalization wins when both set the
+    /// same key.
+    pub fn add_custom_arg(&mut self, key: &str, value: &str) {
+        self.custom_args
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from(value));
+    }
+
+    /// Schedule the delivery of the message, as a unix timestamp. SendGrid rejects messages
+    /// scheduled more than `MAX_SCHEDULE_AHEAD` in the future, which `validate` checks.
+    ///
+    /// Recipients can be delivered to at different times by giving their personalizations their
+    /// own `send_at`, which takes precedence over this one. Schedules further apart than 72 hours
+    /// need separate messages, sent in batches as each one comes within range.
+    pub fn set_send_at(&mut self, send_at: u64) {
+        self.send_at = Some(send_at);
+    }
+
+    /// Schedule the delivery of the message from a chrono timestamp. This requires the `chrono`
+    /// feature.
+    #[cfg(feature = "chrono")]
+    pub fn set_send_at_chrono(&mut self, time: DateTime<Utc>) {
+        self.set_send_at(time.timestamp().max(0) as u64)
+    }
+
+    /// Group the message into a batch, so a scheduled send can be paused or cancelled. The ID
+    /// must come from SendGrid, such as from `SGClient::generate_batch_id`.
+    pub fn set_batch_id(&mut self, batch_id: &str) {
+        self.batch_id = Some(String::from(batch_id));
+    }
+
+    /// Send the message from the dedicated IPs in the named pool. By default SendGrid chooses
+    /// the pool.
+    pub fn set_ip_pool_name(&mut self, ip_pool_name: &str) {
+        self.ip_pool_name = Some(String::from(ip_pool_name));
+    }
+
+    /// Set the unsubscribe group the message belongs to, so recipients can opt out of just the
+    /// messages in that group, and the groups shown when they manage their subscriptions. An
+    /// empty list of groups to display leaves the choice to SendGrid. The groups must already
+    /// exist in the SendGrid account.
+    pub fn set_asm(&mut self, group_id: u64, groups_to_display: Vec<u64>) {
+        self.asm = Some(Asm {
+            group_id,
+            groups_to_display,
+        });
+    }
+
+    /// Add a personalization to the message.
+    pub fn add_personalization(&mut self, p: Personalization) {
+        self.personalizations.push(p);
+    }
+
+    /// Add a personalization for each of the given recipients, so that every recipient gets
+    /// their own copy of the message and can't see who else it was sent to. This differs from
+    /// adding all of them to one `Personalization`, which sends a single message that lists
+    /// every address in its to field.
+    pub fn add_individual_recipients<I: IntoIterator<Item = Email>>(&mut self, recipients: I) {
+        for to in recipients {
+            let mut p = Personalization::new();
+            p.add_to(to);
+            self.add_personalization(p);
+        }
+    }
+
+    /// Check the message for mistakes SendGrid would reject it for. `SGClient::send` calls this
+    /// before sending. It checks that:
+    ///
+    /// - there are at most `MAX_RECIPIENTS` recipients across all personalizations, or returns
+    ///   `SendgridErrorKind::TooManyRecipients`;
+    /// - there is a subject, a template, or a subject on every personalization, or returns
+    ///   `SendgridErrorKind::MissingSubject`;
+    /// - no `send_at` is more than `MAX_SCHEDULE_AHEAD` (72 hours) away, or returns
+    ///   `SendgridErrorKind::ScheduledTooFar`.
+    pub fn validate(&self) -> SendgridResult<()> {
+        let count = self
+            .personalizations
+            .iter()
+            .map(Personalization::recipients)
+            .sum();
+        if count > MAX_RECIPIENTS {
+            return Err(SendgridErrorKind::TooManyRecipients(count, MAX_RECIPIENTS).into());
+        }
+
+        let personalized_subjects = !self.personalizations.is_empty()
+            && self.personalizations.iter().all(|p| p.subject.is_some());
+        if self.subject.is_empty() && self.template_id.is_none() && !personalized_subjects {
+            return Err(SendgridErrorKind::MissingSubject.into());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let latest = now + MAX_SCHEDULE_AHEAD.as_secs();
+        let schedules = self.personalizations.iter().map(|p| p.send_at);
+        if let Some(send_at) = schedules
+            .chain(Some(self.send_at))
+            .flatten()
+            .find(|&send_at| send_at > latest)
+        {
+            return Err(SendgridErrorKind::ScheduledTooFar(send_at, latest).into());
+        }
+        Ok(())
+    }
+
+    /// Add an attachment to the message.
+    pub fn add_attachment(&mut self, a: Attachment) {
+        match self.attachments {
+            None => {
+                self.attachments = Some(vec![a]);
+            }
+            Some(ref mut attachments) => attachments.push(a),
+        };
+    }
+
+    /// Turn sandbox mode on or off. In sandbox mode SendGrid validates the message and responds
+    /// as usual, but doesn't deliver it, which makes it safe to use in tests. The V2 API has no
+    /// equivalent.
+    pub fn set_sandbox_mode(&mut self, enable: bool) {
+        self.mail_settings().sandbox_mode = Some(Setting { enable });
+    }
+
+    /// Deliver the message even to addresses on a suppression list, such as unsubscribed or
+    /// bounced recipients. This should only be used for messages that must always arrive, like
+    /// password resets.
+    pub fn set_bypass_list_management(&mut self, enable: bool) {
+        self.mail_settings().bypass_list_management = Some(Setting { enable });
+    }
+
+    /// Append a footer to the message, with one version for the plain text content and one for
+    /// the HTML content.
+    pub fn set_footer(&mut self, text: &str, html: &str) {
+        self.mail_settings().footer = Some(Footer {
+            enable: true,
+            text: String::from(text),
+            html: String::from(html),
+        });
+    }
+
+    /// Check the message for spam before sending it. Messages scoring at or above the threshold,
+    /// which goes from 1 for the strictest to 10 for the most lenient, are dropped. If a URL is
+    /// given, SendGrid posts a copy of the report to it.
+    pub fn set_spam_check(&mut self, threshold: u8, post_to_url: Option<&str>) {
+        self.mail_settings().spam_check = Some(SpamCheck {
+            enable: true,
+            threshold,
+            post_to_url: post_to_url.map(String::from),
+        });
+    }
+
+    fn mail_settings(&mut self) -> &mut MailSettings {
+        self.mail_settings.get_or_insert_with(MailSettings::default)
+    }
+
+    /// Turn click tracking on or off. When it is on, links in the HTML content are rewritten so
+    /// SendGrid can record clicks, and `enable_text` does the same for the plain text content.
+    /// Passing `true, false` tracks links in the HTML content only.
+    ///
+    /// Individual links can be left alone while tracking is on by adding a `clicktracking=off`
+    /// attribute to their anchor tag in the HTML content, such as
+    /// `<a clicktracking=off href="https://example.com/unsubscribe">`. This is worth doing for
+    /// unsubscribe links, which may stop working for some recipients once rewritten. Links in
+    /// the plain text content have no such attribute, so text tracking should be left off if
+    /// any of them must not be rewritten. Unsubscribe links inserted by subscription tracking
+    /// are never rewritten.
+    pub fn set_click_tracking(&mut self, enable: bool, enable_text: bool) {
+        self.tracking_settings().click_tracking = Some(ClickTracking {
+            enable,
+            enable_text,
+        });
+    }
+
+    /// Turn open tracking on or off. Opens are tracked with an invisible image in the HTML
+    /// content.
+    pub fn set_open_tracking(&mut self, enable: bool) {
+        self.tracking_settings().open_tracking = Some(Setting { enable });
+    }
+
+    /// Turn subscription tracking on or off, which adds an unsubscribe link to the message.
+    pub fn set_subscription_tracking(&mut self, enable: bool) {
+        self.tracking_settings().subscription_tracking = Some(Setting { enable });
+    }
+
+    fn tracking_settings(&mut self) -> &mut TrackingSettings {
+        self.tracking_settings
+            .get_or_insert_with(TrackingSettings::default)
+    }
+
+    /// Set a key that identifies this message, which is sent in an `Idempotency-Key` header with
+    /// every attempt to send it, including retries. Like `Mail::set_idempotency_key`, it is for
+    /// a deduplication layer in front of SendGrid rather than SendGrid itself.
+    pub fn set_idempotency_key<T: Into<String>>(&mut self, key: T) {
+        self.idempotency_key = Some(key.into());
+    }
+
+    // The idempotency key sent with the message, if one was set.
+    pub(crate) fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    /// Add a header to the HTTP request that sends the message, rather than to the message
+    /// itself like `Personalization::add_headers`. Like `Mail::add_request_header`, it can't
+    /// replace the headers the client sets, such as `Authorization` or `Content-Type`.
+    pub fn add_request_header<T: Into<String>, U: Into<String>>(&mut self, name: T, value: U) {
+        self.request_headers.insert(name.into(), value.into());
+    }
+
+    /// Set an `X-Correlation-Id` header on the HTTP request that sends the message, so the send
+    /// can be traced across logs.
+    pub fn set_correlation_id<T: Into<String>>(&mut self, id: T) {
+        self.add_request_header("X-Correlation-Id", id)
+    }
+
+    // The extra headers sent with the HTTP request.
+    pub(crate) fn request_headers(&self) -> Vec<(&str, &str)> {
+        let headers = self.request_headers.iter();
+        headers
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    // The filename and encoded size of each attachment, for checking them against the limits of
+    // the client.
+    pub(crate) fn attachment_sizes(&self) -> Vec<(&str, usize)> {
+        let attachments = self.attachments.iter().flatten();
+        attachments
+            .map(|a| (a.filename.as_str(), a.content.len()))
+            .collect()
+    }
+
+    #[cfg(feature = "blocking")]
+    fn gen_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl Default for Email {
+    fn default() -> Email {
+        Email::new()
+    }
+}
+
+impl Email {
+    /// Construct a new email type.
+    pub fn new() -> Email {
+        Email {
+            email: String::new(),
+            name: None,
+        }
+    }
+
+    /// Set the address for this email.
+    pub fn set_email(&mut self, email: &str) {
+        self.email = String::from(email);
+    }
+
+    /// Set an optional name.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(String::from(name));
+    }
+}
+
+impl From<Destination> for Email {
+    /// An empty name is treated the same as no name.
+    fn from(dest: Destination) -> Email {
+        Email {
+            email: dest.address,
+            name: dest.name.filter(|name| !name.is_empty()),
+        }
+    }
+}
+
+impl Default for Content {
+    fn default() -> Content {
+        Content::new()
+    }
+}
+
+impl Content {
+    /// Construct a new content type.
+    pub fn new() -> Content {
+        Content {
+            content_type: String::new(),
+            value: String::new(),
+        }
+    }
+
+    /// Set the type of this content.
+    pub fn set_content_type(&mut self, content_type: &str) {
+        self.content_type = String::from(content_type);
+    }
+
+    /// Set the corresponding message for this content.
+    pub fn set_value(&mut self, value: &str) {
+        self.value = String::from(value);
+    }
+}
+
+impl Default for Personalization {
+    fn default() -> Personalization {
+        Personalization::new()
+    }
+}
+
+impl Personalization {
+    /// Construct a new personalization block for this message.
+    pub fn new() -> Personalization {
+        Personalization {
+            to: Vec::new(),
+            cc: None,
+            bcc: None,
+            subject: None,
+            headers: None,
+            substitutions: None,
+            custom_args: None,
+            send_at: None,
+            dynamic_template_data: None,
+        }
+    }
+
+    // The number of to, cc and bcc addresses.
+    fn recipients(&self) -> usize {
+        let copies = self.cc.iter().chain(&self.bcc).map(Vec::len).sum::<usize>();
+        self.to.len() + copies
+    }
+
+    /// Add a to field.
+    pub fn add_to(&mut self, to: Email) {
+        self.to.push(to);
+    }
+
+    /// Add a CC field.
+    pub fn add_cc(&mut self, cc: Email) {
+        match self.cc {
+            None => {
+                self.cc = Some(vec![cc]);
+            }
+            Some(ref mut c) => {
+                c.push(cc);
+            }
+        }
+    }
+
+    /// Add a BCC field.
+    pub fn add_bcc(&mut self, bcc: Email) {
+        match self.bcc {
+            None => {
+                self.bcc = Some(vec![bcc]);
+            }
+            Some(ref mut b) => {
+                b.push(bcc);
+            }
+        }
+    }
+
+    /// Add a headers field.
+    pub fn add_headers(&mut self, headers: SGMap) {
+        match self.headers {
+            None => {
+                let mut h = HashMap::new();
+                for (name, value) in headers {
+                    h.insert(name, value);
+                }
+                self.headers = Some(h);
+            }
+            Some(ref mut h) => {
+                h.extend(headers);
+            }
+        }
+    }
+
+    /// Set a subject for the recipients of this personalization, overriding the subject of
+    /// the message.
+    pub fn set_subject(&mut self, subject: &str) {
+        self.subject = Some(String::from(subject));
+    }
+
+    /// Add substitutions for the recipients of this personalization. Each key is replaced by its
+    /// value wherever it appears in the subject and content.
+    pub fn add_substitutions(&mut self, substitutions: SGMap) {
+        self.substitutions
+            .get_or_insert_with(HashMap::new)
+            .extend(substitutions);
+    }
+
+    /// Add custom arguments for the recipients of this personalization. These take precedence
+    /// over the custom arguments of the message.
+    pub fn add_custom_args(&mut self, custom_args: SGMap) {
+        self.custom_args
+            .get_or_insert_with(HashMap::new)
+            .extend(custom_args);
+    }
+
+    /// Add a single custom argument for the recipients of this personalization. It takes
+    /// precedence over a custom argument of the message with the same key.
+    pub fn add_custom_arg(&mut self, key: &str, value: &str) {
+        self.custom_args
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from(value));
+    }
+
+    /// Schedule the delivery of this personalization, as a unix timestamp. This takes precedence
+    /// over the `send_at` of the message.
+    pub fn set_send_at(&mut self, send_at: u64) {
+        self.send_at = Some(send_at);
+    }
+
+    /// Schedule the delivery of this personalization from a chrono timestamp. This requires the
+    /// `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn set_send_at_chrono(&mut self, time: DateTime<Utc>) {
+        self.set_send_at(time.timestamp().max(0) as u64)
+    }
+
+    /// Add data for the dynamic template of the message. When both the existing and the new data
+    /// are objects their keys are merged, otherwise the new data replaces the old.
+    pub fn add_dynamic_template_data(&mut self, data: Value) {
+        match (&mut self.dynamic_template_data, data) {
+            (Some(Value::Object(existing)), Value::Object(new)) => existing.extend(new),
+            (existing, data) => *existing = Some(data),
+        }
+    }
+}
+
+impl Default for Attachment {
+    fn default() -> Attachment {
+        Attachment::new()
+    }
+}
+
+impl Attachment {
+    /// Construct a new attachment for this message.
+    pub fn new() -> Attachment {
+        Attachment {
+            content: String::new(),
+            filename: String::new(),
+            mime_type: None,
+            disposition: None,
+            content_id: None,
+        }
+    }
+
+    /// The raw body of the attachment.
+    pub fn set_content(&mut self, c: &[u8]) {
+        self.content = BASE64.encode(c);
+    }
+
+    /// The body of the attachment when it is already base64 encoded. Like
+    /// `Mail::add_attachment_base64`, URL-safe or unpadded content is converted to the standard,
+    /// padded base64 SendGrid requires. Set the filename first, since errors refer to it.
+    pub fn set_base64_content(&mut self, encoded: &str) -> SendgridResult<()> {
+        self.content = normalize_base64(&self.filename, encoded)?;
+        Ok(())
+    }
+
+    /// Sets the filename for the attachment.
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = filename.into();
+    }
+
+    /// Set an optional mime type. Sendgrid will default to 'application/octet-stream'
+    /// if unspecified.
+    pub fn set_mime_type(&mut self, mime: &str) {
+        self.mime_type = Some(String::from(mime));
+    }
+
+    /// Set how the attachment is presented to the recipient. SendGrid treats attachments as
+    /// downloadable unless told otherwise.
+    pub fn set_disposition(&mut self, disposition: Disposition) {
+        match disposition {
+            Disposition::Attachment => {
+                self.disposition = Some(String::from("attachment"));
+                self.content_id = None;
+            }
+            Disposition::Inline { content_id } => {
+                self.disposition = Some(String::from("inline"));
+                self.content_id = Some(content_id);
+            }
+        }
+    }
+}
+
+#[test]
+fn personalizations_json() {
+    let mut m = SGMailV3::new();
+    for (address, name) in &[("a@example.com", "A"), ("b@example.com", "B")] {
+        let mut to = Email::new();
+        to.set_email(address);
+        let mut p = Personalization::new();
+        p.add_to(to);
+        let mut subs = SGMap::new();
+        subs.insert("-name-".to_string(), name.to_string());
+        p.add_substitutions(subs);
+        m.add_personalization(p);
+    }
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["personalizations"],
+        serde_json::json!([
+            {"to": [{"email": "a@example.com"}], "substitutions": {"-name-": "A"}},
+            {"to": [{"email": "b@example.com"}], "substitutions": {"-name-": "B"}},
+        ])
+    );
+}
+
+#[test]
+fn personalization_subject_json() {
+    let mut m = SGMailV3::new();
+    m.set_subject("Your order shipped");
+    let mut p = Personalization::new();
+    p.set_subject("Your order #1234 shipped");
+    m.add_personalization(p);
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(got["subject"], "Your order shipped");
+    assert_eq!(
+        got["personalizations"][0]["subject"],
+        "Your order #1234 shipped"
+    );
+}
+
+#[test]
+fn individual_recipients_json() {
+    let mut m = SGMailV3::new();
+    m.add_individual_recipients(["a@example.com", "b@example.com"].iter().map(|address| {
+        let mut to = Email::new();
+        to.set_email(address);
+        to
+    }));
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["personalizations"],
+        serde_json::json!([
+            {"to": [{"email": "a@example.com"}]},
+            {"to": [{"email": "b@example.com"}]},
+        ])
+    );
+}
+
+#[test]
+fn reply_to_json() {
+    let email = |address: &str| {
+        let mut e = Email::new();
+        e.set_email(address);
+        e
+    };
+
+    let mut m = SGMailV3::new();
+    m.add_reply_to(email("a@example.com"));
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["reply_to"],
+        serde_json::json!({"email": "a@example.com"})
+    );
+    assert!(got.get("reply_to_list").is_none());
+
+    m.add_reply_to(email("b@example.com"));
+    m.add_reply_to(email("c@example.com"));
+    let got = serde_json::to_value(&m).unwrap();
+    assert!(got.get("reply_to").is_none());
+    assert_eq!(
+        got["reply_to_list"],
+        serde_json::json!([
+            {"email": "a@example.com"},
+            {"email": "b@example.com"},
+            {"email": "c@example.com"},
+        ])
+    );
+
+    m.set_reply_to(email("d@example.com"));
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["reply_to"],
+        serde_json::json!({"email": "d@example.com"})
+    );
+    assert!(got.get("reply_to_list").is_none());
+}
+
+#[test]
+fn recipient_limit() {
+    let mut m = SGMailV3::new();
+    m.set_subject("Test");
+    m.add_individual_recipients((0..MAX_RECIPIENTS - 1).map(|_| Email::new()));
+    m.validate().unwrap();
+
+    let mut p = Personalization::new();
+    p.add_to(Email::new());
+    p.add_bcc(Email::new());
+    m.add_personalization(p);
+    assert!(m
+        .validate()
+        .unwrap_err()
+        .is_kind(&SendgridErrorKind::TooManyRecipients(1001, MAX_RECIPIENTS)));
+}
+
+#[test]
+fn schedule_limit() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut m = SGMailV3::new();
+    m.set_subject("Test");
+    m.set_send_at(now + 3600);
+    let mut p = Personalization::new();
+    p.set_send_at(now + 7200);
+    m.add_personalization(p);
+    m.validate().unwrap();
+    assert_eq!(serde_json::to_value(&m).unwrap()["send_at"], now + 3600);
+
+    let too_far = now + MAX_SCHEDULE_AHEAD.as_secs() + 3600;
+    let mut p = Personalization::new();
+    p.set_send_at(too_far);
+    m.add_personalization(p);
+    match m.validate().unwrap_err().kind() {
+        SendgridErrorKind::ScheduledTooFar(send_at, _) => assert_eq!(*send_at, too_far),
+        other => panic!("expected a scheduling error, got {:?}", other),
+    }
+}
+
+#[test]
+fn subject_or_template() {
+    let mut m = SGMailV3::new();
+    m.add_personalization(Personalization::new());
+    assert!(m
+        .validate()
+        .unwrap_err()
+        .is_kind(&SendgridErrorKind::MissingSubject));
+    assert!(serde_json::to_value(&m).unwrap().get("subject").is_none());
+
+    m.set_template_id("d-123");
+    m.validate().unwrap();
+
+    let mut m = SGMailV3::new();
+    let mut p = Personalization::new();
+    p.set_subject("Your order shipped");
+    m.add_personalization(p);
+    m.validate().unwrap();
+}
+
+#[test]
+fn from_destination_json() {
+    let mut m = SGMailV3::new();
+    m.set_from(Destination::with_name("news@example.com", "Example News"));
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["from"],
+        serde_json::json!({"email": "news@example.com", "name": "Example News"})
+    );
+
+    m.set_from(Destination::new("news@example.com"));
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["from"],
+        serde_json::json!({"email": "news@example.com"})
+    );
+}
+
+#[test]
+fn asm_json() {
+    let mut m = SGMailV3::new();
+    m.set_asm(7, vec![7, 8]);
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["asm"],
+        serde_json::json!({"group_id": 7, "groups_to_display": [7, 8]})
+    );
+
+    m.set_asm(7, Vec::new());
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(got["asm"], serde_json::json!({"group_id": 7}));
+}
+
+#[test]
+fn dynamic_template_json() {
+    let mut m = SGMailV3::new();
+    m.set_template_id("d-123");
+    let mut p = Personalization::new();
+    p.add_dynamic_template_data(serde_json::json!({"name": "A"}));
+    p.add_dynamic_template_data(serde_json::json!({"items": [1, 2]}));
+    m.add_personalization(p);
+
+    m.set_ip_pool_name("transactional");
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(got["template_id"], "d-123");
+    assert_eq!(got["ip_pool_name"], "transactional");
+    assert!(got.get("content").is_none());
+    assert_eq!(
+        got["personalizations"][0]["dynamic_template_data"],
+        serde_json::json!({"name": "A", "items": [1, 2]})
+    );
+}
+
+#[test]
+fn custom_args_json() {
+    let mut m = SGMailV3::new();
+    m.add_custom_arg("source", "signup");
+    let mut p = Personalization::new();
+    p.add_custom_arg("user_id", "42");
+    m.add_personalization(p);
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(got["custom_args"], serde_json::json!({"source": "signup"}));
+    assert_eq!(
+        got["personalizations"][0]["custom_args"],
+        serde_json::json!({"user_id": "42"})
+    );
+}
+
+#[test]
+fn tracking_settings_json() {
+    let mut m = SGMailV3::new();
+    let got = serde_json::to_value(&m).unwrap();
+    assert!(got.get("tracking_settings").is_none());
+    assert!(got.get("ip_pool_name").is_none());
+
+    m.set_click_tracking(true, false);
+    m.set_open_tracking(false);
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["tracking_settings"],
+        serde_json::json!({
+            "click_tracking": {"enable": true, "enable_text": false},
+            "open_tracking": {"enable": false},
+        })
+    );
+}
+
+#[test]
+fn mail_settings_json() {
+    let mut m = SGMailV3::new();
+    m.set_bypass_list_management(true);
+    m.set_footer("Thanks", "<p>Thanks</p>");
+    m.set_spam_check(5, None);
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["mail_settings"],
+        serde_json::json!({
+            "bypass_list_management": {"enable": true},
+            "footer": {"enable": true, "text": "Thanks", "html": "<p>Thanks</p>"},
+            "spam_check": {"enable": true, "threshold": 5},
+        })
+    );
+}
+
+#[test]
+fn inline_attachment_json() {
+    let mut a = Attachment::new();
+    a.set_content(b"png");
+    a.set_filename("logo.png");
+    a.set_disposition(Disposition::Inline {
+        content_id: String::from("logo"),
+    });
+
+    let got = serde_json::to_value(&a).unwrap();
+    assert_eq!(
+        got,
+        serde_json::json!({
+            "content": "cG5n",
+            "filename": "logo.png",
+            "disposition": "inline",
+            "content_id": "logo",
+        })
+    );
+}
+
+#[test]
+fn content_parts_json() {
+    let mut m = SGMailV3::new();
+    m.add_content_part("text/calendar", "BEGIN:VCALENDAR");
+    m.add_content_part("text/html", "<p>Hi</p>");
+    m.add_content_part("text/plain", "Hi");
+
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["content"],
+        serde_json::json!([
+            {"type": "text/plain", "value": "Hi"},
+            {"type": "text/html", "value": "<p>Hi</p>"},
+            {"type": "text/calendar", "value": "BEGIN:VCALENDAR"},
+        ])
+    );
+}
+
+#[test]
+fn sandbox_mode_json() {
+    let mut m = SGMailV3::new();
+    let got = serde_json::to_value(&m).unwrap();
+    assert!(got.get("mail_settings").is_none());
+
+    m.set_sandbox_mode(true);
+    let got = serde_json::to_value(&m).unwrap();
+    assert_eq!(
+        got["mail_settings"],
+        serde_json::json!({"sandbox_mode": {"enable": true}})
+    );
+}
@@ -0,0 +1,156 @@
+//! Access to SendGrid's email address validation API.
+
+use crate::errors::{SendgridError, SendgridErrorKind, SendgridResult};
+use crate::sg_client::{parse_api_response, SGClient};
+
+use reqwest::Method;
+
+static VALIDATION_PATH: &str = "v3/validations/email";
+static VALIDATION_SCOPE: &str = "validations.email.create";
+
+/// SendGrid's overall judgement of an address.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Verdict {
+    /// The address is likely to receive email.
+    Valid,
+    /// The address may receive email, but sending to it could hurt deliverability.
+    Risky,
+    /// The address is unlikely to receive email.
+    Invalid,
+}
+
+/// The result of validating an address.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct EmailValidation {
+    /// The address that was validated.
+    pub email: String,
+    /// The overall judgement of the address.
+    pub verdict: Verdict,
+    /// How likely the address is to be valid, from 0 to 1.
+    pub score: f64,
+    /// The part of the address before the `@`.
+    pub local: String,
+    /// The domain of the address.
+    pub host: String,
+    /// The individual checks the verdict is based on.
+    pub checks: Checks,
+    /// A suggested correction for a misspelled domain, such as "gmail.com" for "gmial.com".
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+/// The individual checks made when validating an address.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Checks {
+    /// Checks of the domain of the address.
+    pub domain: DomainChecks,
+    /// Checks of the part of the address before the `@`.
+    pub local_part: LocalPartChecks,
+    /// Checks of how mail to the address has fared before.
+    pub additional: AdditionalChecks,
+}
+
+/// Checks of the domain of an address.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DomainChecks {
+    /// Whether the address is syntactically valid.
+    pub has_valid_address_syntax: bool,
+    /// Whether the domain has a DNS record that mail can be delivered to.
+    pub has_mx_or_a_record: bool,
+    /// Whether the domain looks like it belongs to a disposable email service.
+    pub is_suspected_disposable_address: bool,
+}
+
+/// Checks of the part of an address before the `@`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LocalPartChecks {
+    /// Whether the address looks like it belongs to a role, such as "admin@", rather than a
+    /// person.
+    pub is_suspected_role_address: bool,
+}
+
+/// Checks of how mail to an address has fared before.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AdditionalChecks {
+    /// Whether mail to the address is known to have bounced.
+    pub has_known_bounces: bool,
+    /// Whether mail to the address is suspected to bounce.
+    pub has_suspected_bounces: bool,
+}
+
+// The validation is wrapped in a result object.
+#[derive(Deserialize)]
+struct ValidationBody {
+    result: EmailValidation,
+}
+
+// The body of a validation request.
+#[derive(Serialize)]
+struct ValidationRequest<'a> {
+    email: &'a str,
+}
+
+// Decode the response to a validation. Keys without the validation scope are rejected with a 403,
+// which is reported as its own error kind caused by the API error.
+fn parse_validation(status: u16, raw_body: String) -> SendgridResult<EmailValidation> {
+    match parse_api_response::<ValidationBody>(status, raw_body) {
+        Ok(body) => Ok(body.result),
+        Err(err) => match *err.kind() {
+            SendgridErrorKind::Api(403, _, _) => Err(SendgridError::with_chain(
+                err,
+                SendgridErrorKind::MissingScope(String::from(VALIDATION_SCOPE)),
+            )),
+            _ => Err(err),
+        },
+    }
+}
+
+impl SGClient {
+    /// Asks SendGrid how likely an address is to receive email. This checks far more than
+    /// `Mail::validate`, such as whether the domain accepts mail, but costs an API call. It needs
+    /// an API key with the `validations.email.create` scope, and fails with
+    /// `SendgridErrorKind::MissingScope` otherwise.
+    #[cfg(feature = "blocking")]
+    pub fn validate_email(&self, email: &str) -> SendgridResult<EmailValidation> {
+        let body = serde_json::to_string(&ValidationRequest { email })?;
+        let (status, body) = self.api_request(Method::POST, VALIDATION_PATH, Some(body))?;
+        parse_validation(status, body)
+    }
+
+    /// The asynchronous version of `validate_email`.
+    pub async fn validate_email_async(&self, email: &str) -> SendgridResult<EmailValidation> {
+        let body = serde_json::to_string(&ValidationRequest { email })?;
+        let (status, body) = self
+            .api_request_async(Method::POST, VALIDATION_PATH, Some(body))
+            .await?;
+        parse_validation(status, body)
+    }
+}
+
+#[test]
+fn parse_validation_response() {
+    let body = String::from(
+        r#"{"result": {"email": "a@gmial.com", "verdict": "Risky", "score": 0.2,
+            "local": "a", "host": "gmial.com", "suggestion": "gmail.com",
+            "checks": {"domain": {"has_valid_address_syntax": true, "has_mx_or_a_record": false,
+                                  "is_suspected_disposable_address": false},
+                       "local_part": {"is_suspected_role_address": false},
+                       "additional": {"has_known_bounces": false, "has_suspected_bounces": true}},
+            "ip_address": "192.0.2.1"}}"#,
+    );
+    let got = parse_validation(200, body).unwrap();
+    assert_eq!(got.verdict, Verdict::Risky);
+    assert_eq!(got.suggestion.as_deref(), Some("gmail.com"));
+    assert!(got.checks.domain.has_valid_address_syntax);
+    assert!(got.checks.additional.has_suspected_bounces);
+
+    let body = String::from(r#"{"errors": [{"field": null, "message": "access forbidden"}]}"#);
+    match parse_validation(403, body) {
+        Err(SendgridError(SendgridErrorKind::MissingScope(_), _)) => {}
+        other => panic!("expected a missing scope error, got {:?}", other),
+    }
+}